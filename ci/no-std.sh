@@ -0,0 +1,12 @@
+#!/usr/bin/env bash
+# Checks the crate without the `std` feature: builds it for a target that has no standard library at all, then runs the tests, which exercise the `spin` locks used in place of `parking_lot`.
+set -euo pipefail
+cd "$(dirname "$0")/.."
+
+target=thumbv7em-none-eabihf
+rustup target add "$target"
+
+cargo build --no-default-features --target "$target"
+cargo build --no-default-features --features futures-core,serde --target "$target"
+cargo test --no-default-features
+cargo test --no-default-features --features futures-core
@@ -0,0 +1,93 @@
+use crate::sync::spin_loop;
+use crate::sync::AtomicUsize;
+use crate::sync::UnsafeCell;
+use core::sync::atomic::Ordering::AcqRel;
+use core::sync::atomic::Ordering::Acquire;
+use core::sync::atomic::Ordering::Release;
+use core::task::Waker;
+
+const WAITING: usize = 0;
+const REGISTERING: usize = 0b01;
+const WAKING: usize = 0b10;
+
+// A slot holding at most one `Waker` that can be registered and woken concurrently without a lock. This is the same protocol as `futures::task::AtomicWaker`: `register` and `take` each claim the slot with a state bit, and whichever side loses the race hands the wake over to the other instead of blocking.
+pub(crate) struct AtomicWaker {
+  state: AtomicUsize,
+  waker: UnsafeCell<Option<Waker>>,
+}
+
+// The `waker` cell is only accessed by whoever holds the REGISTERING or WAKING bit.
+unsafe impl Send for AtomicWaker {}
+unsafe impl Sync for AtomicWaker {}
+
+impl AtomicWaker {
+  pub(crate) fn new() -> AtomicWaker {
+    AtomicWaker {
+      state: AtomicUsize::new(WAITING),
+      waker: UnsafeCell::new(None),
+    }
+  }
+
+  // Only one task should call this at a time; concurrent registrations are not lost but one of them is picked arbitrarily.
+  pub(crate) fn register(&self, waker: &Waker) {
+    match self
+      .state
+      .compare_exchange(WAITING, REGISTERING, Acquire, Acquire)
+      .unwrap_or_else(|s| s)
+    {
+      WAITING => {
+        self.waker.with_mut(|slot| {
+          // SAFETY: We hold the REGISTERING bit, so nothing else is accessing the slot.
+          let slot = unsafe { &mut *slot };
+          match slot {
+            Some(old) if old.will_wake(waker) => {}
+            _ => *slot = Some(waker.clone()),
+          };
+        });
+        if let Err(state) = self
+          .state
+          .compare_exchange(REGISTERING, WAITING, AcqRel, Acquire)
+        {
+          // A concurrent `take` ran while we were registering and left the wake to us.
+          debug_assert_eq!(state, REGISTERING | WAKING);
+          // SAFETY: The WAKING side backs off while REGISTERING is set, so the slot is still ours.
+          let waker = self.waker.with_mut(|slot| unsafe { (*slot).take() });
+          self.state.swap(WAITING, AcqRel);
+          if let Some(waker) = waker {
+            waker.wake();
+          };
+        };
+      }
+      WAKING => {
+        // A wake is in progress, so the new waker would miss it; wake it directly so it polls again.
+        waker.wake_by_ref();
+        spin_loop();
+      }
+      state => {
+        debug_assert!(state == REGISTERING || state == REGISTERING | WAKING);
+      }
+    };
+  }
+
+  pub(crate) fn take(&self) -> Option<Waker> {
+    match self.state.fetch_or(WAKING, AcqRel) {
+      WAITING => {
+        // SAFETY: We hold the WAKING bit and nobody was registering, so the slot is ours.
+        let waker = self.waker.with_mut(|slot| unsafe { (*slot).take() });
+        self.state.fetch_and(!WAKING, Release);
+        waker
+      }
+      state => {
+        // Either a registration is in progress and will perform the wake when it sees the WAKING bit, or another `take` is already waking.
+        debug_assert!(state == REGISTERING || state == REGISTERING | WAKING || state == WAKING);
+        None
+      }
+    }
+  }
+
+  pub(crate) fn wake(&self) {
+    if let Some(waker) = self.take() {
+      waker.wake();
+    };
+  }
+}
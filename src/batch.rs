@@ -0,0 +1,442 @@
+use crate::event::AutoResetEvent;
+use crate::event::AutoResetEventController;
+use crate::sync::Arc;
+use crate::sync::Mutex;
+use crate::TrySignalFuture;
+use crate::TrySignalFutureController;
+use alloc::format;
+use alloc::vec::Vec;
+use core::future::poll_fn;
+use core::future::Future;
+use core::mem::take;
+use core::pin::pin;
+use core::pin::Pin;
+use core::task::Poll;
+use core::time::Duration;
+
+struct BatchState<Req, Resp, E> {
+  pending: Vec<(Req, TrySignalFutureController<Resp, E>)>,
+  // Set once the `BatchRunner` is dropped, after which submissions are canceled immediately.
+  closed: bool,
+}
+
+type Shared<Req, Resp, E> = Arc<Mutex<BatchState<Req, Resp, E>>>;
+
+/// Collects requests from any number of tasks so they can be processed together, e.g. to group-commit writes with a single fsync. Each `submit` returns a future for that request's response; a `BatchRunner` flushes the pending requests whenever enough of them have accumulated or the first of them has waited long enough.
+///
+/// This generalises the `DelayedWriter` pattern from the `SignalFuture` docs. It doesn't depend on any runtime: the runner is a future to spawn wherever is convenient, and it's given the runtime's sleep function.
+///
+/// # Examples
+///
+/// ```ignore
+/// let (batcher, runner) = Batcher::new(64, Duration::from_millis(5));
+/// tokio::spawn(runner.run(
+///   |writes: Vec<(u64, Vec<u8>)>| async move {
+///     let results = fd.write_all_at(&writes).await?;
+///     fd.sync_data().await?;
+///     Ok(results)
+///   },
+///   tokio::time::sleep,
+/// ));
+/// batcher.submit((offset, data)).await?;
+/// ```
+pub struct Batcher<Req, Resp, E> {
+  shared_state: Shared<Req, Resp, E>,
+  max_batch_size: usize,
+  notify: AutoResetEventController,
+}
+
+impl<Req, Resp, E> Clone for Batcher<Req, Resp, E> {
+  fn clone(&self) -> Self {
+    Batcher {
+      shared_state: self.shared_state.clone(),
+      max_batch_size: self.max_batch_size,
+      notify: self.notify.clone(),
+    }
+  }
+}
+
+impl<Req, Resp, E> Batcher<Req, Resp, E> {
+  /// Creates a batcher that flushes once `max_batch_size` requests are pending, or `max_latency` after the first request of a batch was submitted, whichever comes first. Requests submitted while a flush is running are flushed as soon as it finishes. Panics if `max_batch_size` is zero.
+  pub fn new(
+    max_batch_size: usize,
+    max_latency: Duration,
+  ) -> (Batcher<Req, Resp, E>, BatchRunner<Req, Resp, E>) {
+    assert!(max_batch_size > 0, "`Batcher` batch size must be positive");
+    let shared_state = Arc::new(Mutex::new(BatchState {
+      pending: Vec::new(),
+      closed: false,
+    }));
+    let (event, notify) = AutoResetEvent::new();
+
+    (
+      Batcher {
+        shared_state: shared_state.clone(),
+        max_batch_size,
+        notify,
+      },
+      BatchRunner {
+        shared_state: shared_state.clone(),
+        max_batch_size,
+        max_latency,
+        event,
+      },
+    )
+  }
+
+  /// Queues `req` for the next flush. The returned future resolves with this request's response, with `TrySignalError::Failed` holding the error if the whole flush failed, or with `TrySignalError::Canceled` if the `BatchRunner` is dropped first.
+  pub fn submit(&self, req: Req) -> TrySignalFuture<Resp, E> {
+    let (fut, ctl) = TrySignalFuture::new();
+    let notify = {
+      let mut shared_state = self.shared_state.lock();
+      if shared_state.closed {
+        // Dropping the controller cancels the future.
+        return fut;
+      };
+      shared_state.pending.push((req, ctl));
+      // The runner only needs waking to start the latency timer, or to cut it short.
+      let len = shared_state.pending.len();
+      len == 1 || len >= self.max_batch_size
+    };
+    if notify {
+      self.notify.set();
+    };
+    fut
+  }
+}
+
+/// Flushes the requests submitted to a `Batcher`. Created alongside it by `Batcher::new`; `run` must be polled, usually by spawning it, for any request to complete.
+pub struct BatchRunner<Req, Resp, E> {
+  shared_state: Shared<Req, Resp, E>,
+  max_batch_size: usize,
+  max_latency: Duration,
+  event: AutoResetEvent,
+}
+
+impl<Req, Resp, E: Clone> BatchRunner<Req, Resp, E> {
+  fn pending_len(&self) -> usize {
+    self.shared_state.lock().pending.len()
+  }
+
+  /// Flushes batches until every `Batcher` has been dropped and the remaining requests have been flushed. `flush` is called with up to `max_batch_size` requests at a time and must return one response per request, in the same order; if it fails, every request in the batch receives a clone of the error. `sleep` should return a future that completes after the given duration, such as `tokio::time::sleep`.
+  pub async fn run<F, FFut, S, SFut>(self, mut flush: F, mut sleep: S)
+  where
+    F: FnMut(Vec<Req>) -> FFut,
+    FFut: Future<Output = Result<Vec<Resp>, E>>,
+    S: FnMut(Duration) -> SFut,
+    SFut: Future<Output = ()>,
+  {
+    loop {
+      // Once every `Batcher` is gone, no more requests can arrive, so what's left is flushed without waiting.
+      let mut draining = false;
+      // Notifications can be stale, from requests that were flushed in an earlier batch, so this checks for requests again after each one.
+      while self.pending_len() == 0 {
+        if self.event.wait().await.is_err() {
+          if self.pending_len() == 0 {
+            return;
+          };
+          draining = true;
+        };
+      }
+      if !draining && self.pending_len() < self.max_batch_size {
+        self.wait_for_batch(&mut sleep).await;
+      };
+      // Requests submitted while a flush runs have already waited for it, so they are flushed straight after it rather than starting another latency timer.
+      while self.flush_batch(&mut flush).await {}
+    }
+  }
+
+  // Waits until the batch is full, `max_latency` has passed, or every `Batcher` has been dropped.
+  async fn wait_for_batch<S, SFut>(&self, sleep: &mut S)
+  where
+    S: FnMut(Duration) -> SFut,
+    SFut: Future<Output = ()>,
+  {
+    let mut timer = pin!(sleep(self.max_latency));
+    loop {
+      let mut notified = self.event.wait();
+      // Resolves with whether to stop waiting for more requests.
+      let stop = poll_fn(|cx| {
+        if timer.as_mut().poll(cx).is_ready() {
+          return Poll::Ready(true);
+        };
+        match Pin::new(&mut notified).poll(cx) {
+          Poll::Ready(res) => Poll::Ready(res.is_err()),
+          Poll::Pending => Poll::Pending,
+        }
+      })
+      .await;
+      if stop || self.pending_len() >= self.max_batch_size {
+        return;
+      };
+    }
+  }
+
+  // Returns whether requests were submitted while `flush` ran.
+  async fn flush_batch<F, FFut>(&self, flush: &mut F) -> bool
+  where
+    F: FnMut(Vec<Req>) -> FFut,
+    FFut: Future<Output = Result<Vec<Resp>, E>>,
+  {
+    let batch = {
+      let mut shared_state = self.shared_state.lock();
+      let n = shared_state.pending.len().min(self.max_batch_size);
+      shared_state.pending.drain(..n).collect::<Vec<_>>()
+    };
+    if batch.is_empty() {
+      return false;
+    };
+    let (reqs, ctls): (Vec<_>, Vec<_>) = batch.into_iter().unzip();
+    let n = reqs.len();
+    let res = flush(reqs).await;
+    // Checked before responding, as requests submitted in reaction to a response should start a new batch rather than be flushed on their own.
+    let queued = self.pending_len() > 0;
+    match res {
+      Ok(resps) if resps.len() == n => {
+        for (ctl, resp) in ctls.into_iter().zip(resps) {
+          ctl.succeed(resp);
+        }
+      }
+      Ok(resps) => {
+        let reason = format!(
+          "flush returned {} responses for {} requests",
+          resps.len(),
+          n
+        );
+        for ctl in ctls {
+          ctl.abort(reason.clone());
+        }
+      }
+      Err(err) => {
+        for ctl in ctls {
+          ctl.fail(err.clone());
+        }
+      }
+    };
+    queued
+  }
+}
+
+impl<Req, Resp, E> Drop for BatchRunner<Req, Resp, E> {
+  fn drop(&mut self) {
+    let pending = {
+      let mut shared_state = self.shared_state.lock();
+      shared_state.closed = true;
+      take(&mut shared_state.pending)
+    };
+    // Dropping the controllers cancels the requests nobody will flush, which is done outside the lock as it wakes their tasks.
+    drop(pending);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  extern crate std;
+
+  use super::Batcher;
+  use crate::SignalFuture;
+  use crate::SignalFutureController;
+  use crate::TrySignalError;
+  use alloc::string::String;
+  use alloc::string::ToString;
+  use alloc::vec;
+  use alloc::vec::Vec;
+  use core::future::Future;
+  use core::time::Duration;
+  use futures::executor::block_on;
+  use std::sync::mpsc::channel;
+  use std::sync::mpsc::Receiver;
+  use std::sync::Arc;
+  use std::sync::Mutex;
+  use std::thread;
+  use std::thread::JoinHandle;
+  use std::time::Instant;
+
+  fn sleep(duration: Duration) -> impl Future<Output = ()> {
+    let (fut, ctl) = SignalFuture::new();
+    thread::spawn(move || {
+      thread::sleep(duration);
+      ctl.signal(());
+    });
+    async move {
+      let _ = fut.await;
+    }
+  }
+
+  // Runs a batcher whose flush doubles each request after `flush_time`, fails batches containing 99, and returns one response too few for batches containing 98. Returns the size of every batch flushed once the runner stops.
+  fn start(
+    max_batch_size: usize,
+    max_latency: Duration,
+    flush_time: Duration,
+  ) -> (Batcher<u32, u32, String>, JoinHandle<Vec<usize>>) {
+    let (batcher, runner) = Batcher::new(max_batch_size, max_latency);
+    let handle = thread::spawn(move || {
+      let sizes = Arc::new(Mutex::new(Vec::new()));
+      let flushed = sizes.clone();
+      block_on(runner.run(
+        move |reqs: Vec<u32>| {
+          flushed.lock().unwrap().push(reqs.len());
+          async move {
+            sleep(flush_time).await;
+            if reqs.contains(&99) {
+              return Err("bad batch".to_string());
+            };
+            let mut resps: Vec<_> = reqs.iter().map(|r| r * 2).collect();
+            if reqs.contains(&98) {
+              resps.pop();
+            };
+            Ok(resps)
+          }
+        },
+        sleep,
+      ));
+      let sizes = sizes.lock().unwrap().clone();
+      sizes
+    });
+    (batcher, handle)
+  }
+
+  // A sleep or flush that a runner started by `start_scripted` is waiting on, which completes once its controller is signaled.
+  enum Step {
+    Sleep(SignalFutureController),
+    Flush(Vec<u32>, SignalFutureController),
+  }
+
+  // Runs a batcher whose sleeps and flushes only complete when the test signals them, so tests can check the order of flushes without depending on timing. Flushes double each request.
+  fn start_scripted(
+    max_batch_size: usize,
+  ) -> (Batcher<u32, u32, String>, Receiver<Step>, JoinHandle<()>) {
+    let (batcher, runner) = Batcher::new(max_batch_size, Duration::from_secs(60));
+    let (steps, recv) = channel();
+    let slept = steps.clone();
+    let handle = thread::spawn(move || {
+      block_on(runner.run(
+        move |reqs: Vec<u32>| {
+          let (fut, ctl) = SignalFuture::new();
+          let resps = reqs.iter().map(|r| r * 2).collect();
+          steps.send(Step::Flush(reqs, ctl)).unwrap();
+          async move {
+            let _ = fut.await;
+            Ok::<_, String>(resps)
+          }
+        },
+        move |_| {
+          let (fut, ctl) = SignalFuture::new();
+          slept.send(Step::Sleep(ctl)).unwrap();
+          async move {
+            let _ = fut.await;
+          }
+        },
+      ));
+    });
+    (batcher, recv, handle)
+  }
+
+  #[test]
+  fn flushes_full_batch_without_waiting_for_latency() {
+    let (batcher, runner) = start(3, Duration::from_secs(60), Duration::ZERO);
+    let futs: Vec<_> = (1..=3).map(|r| batcher.submit(r)).collect();
+    let resps: Vec<_> = futs.into_iter().map(block_on).collect();
+    assert_eq!(resps, vec![Ok(2), Ok(4), Ok(6)]);
+    drop(batcher);
+    assert_eq!(runner.join().unwrap(), vec![3]);
+  }
+
+  #[test]
+  fn flushes_partial_batch_after_latency() {
+    let (batcher, runner) = start(10, Duration::from_millis(50), Duration::ZERO);
+    let started = Instant::now();
+    let a = batcher.submit(1);
+    let b = batcher.submit(2);
+    assert_eq!(block_on(a), Ok(2));
+    assert_eq!(block_on(b), Ok(4));
+    assert!(started.elapsed() >= Duration::from_millis(50));
+    drop(batcher);
+    assert_eq!(runner.join().unwrap(), vec![2]);
+  }
+
+  #[test]
+  fn flushes_requests_queued_during_flush_immediately() {
+    let (batcher, steps, runner) = start_scripted(10);
+    let first = batcher.submit(1);
+    let Step::Sleep(timer) = steps.recv().unwrap() else {
+      panic!("expected the latency timer to start");
+    };
+    timer.signal(());
+    let Step::Flush(reqs, flushed) = steps.recv().unwrap() else {
+      panic!("expected the first batch to be flushed");
+    };
+    assert_eq!(reqs, vec![1]);
+    let second = batcher.submit(2);
+    flushed.signal(());
+    assert_eq!(block_on(first), Ok(2));
+    // The second request was queued while the first flush ran, so it's flushed next without starting another latency timer.
+    let Step::Flush(reqs, flushed) = steps.recv().unwrap() else {
+      panic!("expected the queued request to be flushed without waiting");
+    };
+    assert_eq!(reqs, vec![2]);
+    flushed.signal(());
+    assert_eq!(block_on(second), Ok(4));
+    drop(batcher);
+    runner.join().unwrap();
+    assert_eq!(steps.iter().count(), 0);
+  }
+
+  #[test]
+  fn request_submitted_after_a_response_starts_a_new_batch() {
+    let (batcher, runner) = start(10, Duration::from_millis(100), Duration::ZERO);
+    assert_eq!(block_on(batcher.submit(1)), Ok(2));
+    let resubmitted_at = Instant::now();
+    let a = batcher.submit(2);
+    let b = batcher.submit(3);
+    assert_eq!(block_on(a), Ok(4));
+    assert_eq!(block_on(b), Ok(6));
+    assert!(resubmitted_at.elapsed() >= Duration::from_millis(100));
+    drop(batcher);
+    assert_eq!(runner.join().unwrap(), vec![1, 2]);
+  }
+
+  #[test]
+  fn aborts_batch_with_wrong_response_count() {
+    let (batcher, runner) = start(2, Duration::from_secs(60), Duration::ZERO);
+    let a = batcher.submit(1);
+    let b = batcher.submit(98);
+    let reason = "flush returned 1 responses for 2 requests".to_string();
+    assert_eq!(block_on(a), Err(TrySignalError::Aborted(reason.clone())));
+    assert_eq!(block_on(b), Err(TrySignalError::Aborted(reason)));
+    drop(batcher);
+    runner.join().unwrap();
+  }
+
+  #[test]
+  fn fails_every_request_with_shared_error() {
+    let (batcher, runner) = start(3, Duration::from_secs(60), Duration::ZERO);
+    let futs: Vec<_> = [1, 99, 3].into_iter().map(|r| batcher.submit(r)).collect();
+    for fut in futs {
+      assert_eq!(
+        block_on(fut),
+        Err(TrySignalError::Failed("bad batch".to_string()))
+      );
+    }
+    drop(batcher);
+    runner.join().unwrap();
+  }
+
+  #[test]
+  fn flushes_remaining_requests_once_batchers_are_dropped() {
+    let (batcher, runner) = start(10, Duration::from_secs(60), Duration::ZERO);
+    let fut = batcher.submit(4);
+    drop(batcher);
+    assert_eq!(block_on(fut), Ok(8));
+    assert_eq!(runner.join().unwrap(), vec![1]);
+  }
+
+  #[test]
+  fn cancels_requests_once_runner_is_dropped() {
+    let (batcher, runner) = Batcher::<u32, u32, String>::new(2, Duration::from_secs(60));
+    let fut = batcher.submit(1);
+    drop(runner);
+    assert_eq!(block_on(fut), Err(TrySignalError::Canceled));
+    assert_eq!(block_on(batcher.submit(2)), Err(TrySignalError::Canceled));
+  }
+}
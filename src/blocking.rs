@@ -0,0 +1,154 @@
+use crate::Canceled;
+use crate::SignalFuture;
+use std::fmt;
+use std::fmt::Display;
+use std::sync::Arc;
+use std::task::Poll;
+use std::task::Wake;
+use std::task::Waker;
+use std::thread;
+use std::thread::Thread;
+use std::time::Duration;
+use std::time::Instant;
+
+// Registered in place of a task's waker while a thread blocks on a `SignalFuture`, so that the controller's usual wakeup unparks the thread instead.
+struct ThreadWaker(Thread);
+
+impl Wake for ThreadWaker {
+  fn wake(self: Arc<Self>) {
+    self.0.unpark();
+  }
+
+  fn wake_by_ref(self: &Arc<Self>) {
+    self.0.unpark();
+  }
+}
+
+fn thread_waker() -> Waker {
+  Waker::from(Arc::new(ThreadWaker(thread::current())))
+}
+
+/// Error returned by `SignalFuture::wait_timeout`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WaitTimeoutError {
+  /// Nothing was signaled before the timeout elapsed. The future can still be waited on or awaited again.
+  Timeout,
+  /// Every controller was dropped without signaling.
+  Canceled,
+}
+
+impl From<Canceled> for WaitTimeoutError {
+  fn from(_: Canceled) -> Self {
+    WaitTimeoutError::Canceled
+  }
+}
+
+impl Display for WaitTimeoutError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      WaitTimeoutError::Timeout => write!(f, "timed out waiting for signal"),
+      WaitTimeoutError::Canceled => Display::fmt(&Canceled, f),
+    }
+  }
+}
+
+impl std::error::Error for WaitTimeoutError {}
+
+impl<T> SignalFuture<T> {
+  /// Blocks the current thread until a value is signaled or every controller is dropped. This is for plain OS threads (e.g. FFI callbacks or rayon workers) that have no executor to await the future with; the thread is parked and unparked by the controller, without spinning up a runtime.
+  ///
+  /// This must not be called from within an async task, as it blocks the executor thread.
+  pub fn wait(mut self) -> Result<T, Canceled> {
+    let waker = thread_waker();
+    loop {
+      if let Poll::Ready(res) = self.poll_with(&waker) {
+        return res;
+      };
+      thread::park();
+    }
+  }
+
+  /// Like `wait`, but gives up after `timeout`. On timeout the future is left intact, so it can be waited on again or awaited.
+  pub fn wait_timeout(&mut self, timeout: Duration) -> Result<T, WaitTimeoutError> {
+    let waker = thread_waker();
+    // A timeout too large to represent is as good as waiting forever.
+    let deadline = Instant::now().checked_add(timeout);
+    loop {
+      if let Poll::Ready(res) = self.poll_with(&waker) {
+        return res.map_err(WaitTimeoutError::from);
+      };
+      match deadline {
+        Some(deadline) => {
+          let now = Instant::now();
+          if now >= deadline {
+            return Err(WaitTimeoutError::Timeout);
+          };
+          thread::park_timeout(deadline - now);
+        }
+        None => thread::park(),
+      };
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::WaitTimeoutError;
+  use crate::Canceled;
+  use crate::SignalFuture;
+  use std::thread;
+  use std::time::Duration;
+
+  #[test]
+  fn wait_is_unparked_by_signal_from_another_thread() {
+    let (fut, ctl) = SignalFuture::new();
+    let signaler = thread::spawn(move || {
+      thread::sleep(Duration::from_millis(20));
+      ctl.signal(1);
+    });
+    assert_eq!(fut.wait(), Ok(1));
+    signaler.join().unwrap();
+  }
+
+  #[test]
+  fn wait_timeout_leaves_future_usable() {
+    let (mut fut, ctl) = SignalFuture::new();
+    assert_eq!(
+      fut.wait_timeout(Duration::from_millis(10)),
+      Err(WaitTimeoutError::Timeout)
+    );
+    thread::spawn(move || ctl.signal(1)).join().unwrap();
+    assert_eq!(fut.wait(), Ok(1));
+  }
+
+  #[test]
+  fn wait_is_canceled_once_controllers_are_dropped() {
+    let (fut, ctl) = SignalFuture::<u32>::new();
+    let other = ctl.clone();
+    let dropper = thread::spawn(move || {
+      drop(ctl);
+      thread::sleep(Duration::from_millis(20));
+      drop(other);
+    });
+    assert_eq!(fut.wait(), Err(Canceled));
+    dropper.join().unwrap();
+
+    let (mut fut, ctl) = SignalFuture::<u32>::new();
+    drop(ctl);
+    assert_eq!(
+      fut.wait_timeout(Duration::from_secs(60)),
+      Err(WaitTimeoutError::Canceled)
+    );
+  }
+
+  #[test]
+  fn wait_timeout_with_max_duration_waits_for_signal() {
+    let (mut fut, ctl) = SignalFuture::new();
+    let signaler = thread::spawn(move || {
+      thread::sleep(Duration::from_millis(20));
+      ctl.signal(1);
+    });
+    assert_eq!(fut.wait_timeout(Duration::MAX), Ok(1));
+    signaler.join().unwrap();
+  }
+}
@@ -0,0 +1,152 @@
+use crate::sync::Mutex;
+use crate::Canceled;
+use crate::SignalFuture;
+use alloc::sync::Arc;
+use alloc::task::Wake;
+use core::task::Poll;
+use core::task::Waker;
+
+struct CallbackState<T, F> {
+  // The future and callback, while nobody is polling them. `None` while they are being polled, and once the callback has run.
+  slot: Option<(SignalFuture<T>, F)>,
+  running: bool,
+  // Set if woken while another thread was polling, so it polls again rather than miss the wakeup.
+  notified: bool,
+}
+
+// Acts as the future's waker, so the thread that signals (or drops the last controller) polls the future and runs the callback itself.
+struct Callback<T, F> {
+  state: Mutex<CallbackState<T, F>>,
+}
+
+impl<T, F> Callback<T, F>
+where
+  T: Send + 'static,
+  F: FnOnce(Result<T, Canceled>) + Send + 'static,
+{
+  fn run(self: &Arc<Self>) {
+    let (mut fut, f) = {
+      let mut state = self.state.lock();
+      if state.running {
+        state.notified = true;
+        return;
+      };
+      let Some(slot) = state.slot.take() else {
+        return;
+      };
+      state.running = true;
+      slot
+    };
+    let waker = Waker::from(self.clone());
+    loop {
+      // Polling can wake this waker synchronously, which only sets `notified` as `running` is set.
+      if let Poll::Ready(res) = fut.poll_with(&waker) {
+        drop(fut);
+        f(res);
+        return;
+      };
+      let mut state = self.state.lock();
+      if state.notified {
+        state.notified = false;
+        continue;
+      };
+      state.slot = Some((fut, f));
+      state.running = false;
+      return;
+    }
+  }
+}
+
+impl<T, F> Wake for Callback<T, F>
+where
+  T: Send + 'static,
+  F: FnOnce(Result<T, Canceled>) + Send + 'static,
+{
+  fn wake(self: Arc<Self>) {
+    self.run();
+  }
+
+  fn wake_by_ref(self: &Arc<Self>) {
+    self.run();
+  }
+}
+
+impl<T: Send + 'static> SignalFuture<T> {
+  /// Runs `f` with the outcome instead of awaiting it, for callback-based code that doesn't poll futures. `f` runs on the thread that signals the future, or that drops the last controller, in which case it receives `Err(Canceled)`. If a value has already been signaled, `f` runs immediately on this thread.
+  ///
+  /// `f` runs while the controller is signaling, so it should be quick and hand off any heavy work.
+  pub fn on_signal(self, f: impl FnOnce(Result<T, Canceled>) + Send + 'static) {
+    let callback = Arc::new(Callback {
+      state: Mutex::new(CallbackState {
+        slot: Some((self, f)),
+        running: false,
+        notified: false,
+      }),
+    });
+    callback.run();
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  extern crate std;
+
+  use crate::Canceled;
+  use crate::SignalFuture;
+  use std::sync::atomic::AtomicUsize;
+  use std::sync::atomic::Ordering::SeqCst;
+  use std::sync::mpsc::channel;
+  use std::sync::Arc;
+  use std::thread;
+
+  #[test]
+  fn runs_on_signaling_thread() {
+    let (fut, ctl) = SignalFuture::new();
+    let (send, recv) = channel();
+    fut.on_signal(move |res| send.send((res, thread::current().id())).unwrap());
+    let signaler = thread::spawn(move || ctl.signal(1));
+    let signaler_id = signaler.thread().id();
+    signaler.join().unwrap();
+    assert_eq!(recv.recv().unwrap(), (Ok(1), signaler_id));
+  }
+
+  #[test]
+  fn runs_immediately_if_already_signaled() {
+    let (fut, ctl) = SignalFuture::new();
+    ctl.signal(1);
+    let (send, recv) = channel();
+    fut.on_signal(move |res| send.send((res, thread::current().id())).unwrap());
+    assert_eq!(recv.try_recv().unwrap(), (Ok(1), thread::current().id()));
+  }
+
+  #[test]
+  fn receives_canceled_once_last_controller_is_dropped() {
+    let (fut, ctl) = SignalFuture::<u32>::new();
+    let other = ctl.clone();
+    let (send, recv) = channel();
+    fut.on_signal(move |res| send.send(res).unwrap());
+    drop(ctl);
+    assert!(recv.try_recv().is_err());
+    drop(other);
+    assert_eq!(recv.try_recv().unwrap(), Err(Canceled));
+  }
+
+  #[test]
+  fn signal_racing_on_signal_runs_callback_once() {
+    for _ in 0..1000 {
+      let (fut, ctl) = SignalFuture::new();
+      let watcher = ctl.clone();
+      let calls = Arc::new(AtomicUsize::new(0));
+      let signaler = thread::spawn(move || ctl.signal(1));
+      let counted = calls.clone();
+      fut.on_signal(move |res| {
+        assert_eq!(res, Ok(1));
+        counted.fetch_add(1, SeqCst);
+      });
+      signaler.join().unwrap();
+      assert_eq!(calls.load(SeqCst), 1);
+      // The future was dropped once the callback ran, rather than kept alive by the callback registered as its own waker.
+      assert!(watcher.is_closed());
+    }
+  }
+}
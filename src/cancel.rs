@@ -0,0 +1,254 @@
+use crate::sync::Mutex;
+use crate::waiters::Waiters;
+use alloc::sync::Arc;
+use alloc::sync::Weak;
+use alloc::vec::Vec;
+use core::future::Future;
+use core::mem::take;
+use core::pin::Pin;
+use core::task::Context;
+use core::task::Poll;
+
+struct CancelState {
+  cancelled: bool,
+  waiters: Waiters,
+  // Tokens created with `child`. They are held weakly so a parent doesn't keep dropped children alive; entries for dropped children are pruned as new ones are added.
+  children: Vec<Weak<Mutex<CancelState>>>,
+  // Pruning scans every entry, so it's only done once `children` has doubled in length since the last prune, keeping `child` amortised O(1).
+  prune_at: usize,
+}
+
+fn cancel(shared_state: &Mutex<CancelState>) {
+  let (wakers, mut pending) = {
+    let mut shared_state = shared_state.lock();
+    if shared_state.cancelled {
+      return;
+    };
+    shared_state.cancelled = true;
+    (
+      shared_state.waiters.take_all(),
+      take(&mut shared_state.children),
+    )
+  };
+  for waker in wakers {
+    waker.wake();
+  }
+  // Descendants are cancelled one at a time, outside their ancestors' locks and without recursing, so deep trees can't overflow the stack.
+  while let Some(child) = pending.pop() {
+    let Some(child) = child.upgrade() else {
+      continue;
+    };
+    let wakers = {
+      let mut child = child.lock();
+      if child.cancelled {
+        continue;
+      };
+      child.cancelled = true;
+      pending.append(&mut child.children);
+      child.waiters.take_all()
+    };
+    for waker in wakers {
+      waker.wake();
+    }
+  }
+}
+
+/// A token for cooperatively cancelling work, replacing the pattern of using a `SignalFuture<()>` as an ad-hoc flag. Clones share the same state, so any of them can cancel and all of them observe it. Tokens created with `child` are cancelled along with their parent, but cancelling a child leaves the parent untouched, which suits trees of tasks where shutting down a subsystem shouldn't stop the whole service.
+///
+/// Unlike a `SignalFuture`, a token is never canceled by dropping handles: it stays uncancelled until `cancel` is called on it or an ancestor.
+#[derive(Clone)]
+pub struct CancellationSignal {
+  shared_state: Arc<Mutex<CancelState>>,
+}
+
+impl CancellationSignal {
+  pub fn new() -> CancellationSignal {
+    CancellationSignal {
+      shared_state: Arc::new(Mutex::new(CancelState {
+        cancelled: false,
+        waiters: Waiters::new(),
+        children: Vec::new(),
+        prune_at: 0,
+      })),
+    }
+  }
+
+  /// Creates a token that is cancelled when this one is. If this token has already been cancelled, so is the child.
+  pub fn child(&self) -> CancellationSignal {
+    let mut shared_state = self.shared_state.lock();
+    let child = Arc::new(Mutex::new(CancelState {
+      cancelled: shared_state.cancelled,
+      waiters: Waiters::new(),
+      children: Vec::new(),
+      prune_at: 0,
+    }));
+    if !shared_state.cancelled {
+      if shared_state.children.len() >= shared_state.prune_at {
+        shared_state.children.retain(|c| c.strong_count() > 0);
+        shared_state.prune_at = shared_state.children.len() * 2;
+      };
+      shared_state.children.push(Arc::downgrade(&child));
+    };
+    CancellationSignal {
+      shared_state: child,
+    }
+  }
+
+  /// Cancels this token and all of its descendants, waking every task waiting in `cancelled`. Cancelling an already cancelled token has no effect.
+  pub fn cancel(&self) {
+    cancel(&self.shared_state);
+  }
+
+  pub fn is_cancelled(&self) -> bool {
+    self.shared_state.lock().cancelled
+  }
+
+  /// Waits until this token is cancelled. Completes immediately if it already is.
+  pub fn cancelled(&self) -> WaitForCancellation<'_> {
+    WaitForCancellation {
+      shared_state: &self.shared_state,
+      key: None,
+      terminated: false,
+    }
+  }
+
+  /// Returns a guard that cancels this token when dropped, e.g. to stop background tasks tied to a scope however it is exited.
+  pub fn drop_guard(self) -> CancellationDropGuard {
+    CancellationDropGuard { signal: Some(self) }
+  }
+}
+
+impl Default for CancellationSignal {
+  fn default() -> Self {
+    CancellationSignal::new()
+  }
+}
+
+/// Future returned by `CancellationSignal::cancelled`.
+pub struct WaitForCancellation<'a> {
+  shared_state: &'a Mutex<CancelState>,
+  key: Option<u64>,
+  terminated: bool,
+}
+
+impl Future for WaitForCancellation<'_> {
+  type Output = ();
+
+  fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+    let this = self.get_mut();
+    assert!(
+      !this.terminated,
+      "`WaitForCancellation` polled after completion"
+    );
+    let mut shared_state = this.shared_state.lock();
+    if shared_state.cancelled {
+      shared_state.waiters.remove(&mut this.key);
+      this.terminated = true;
+      Poll::Ready(())
+    } else {
+      shared_state.waiters.register(&mut this.key, cx.waker());
+      Poll::Pending
+    }
+  }
+}
+
+#[cfg(feature = "futures-core")]
+impl futures_core::FusedFuture for WaitForCancellation<'_> {
+  fn is_terminated(&self) -> bool {
+    self.terminated
+  }
+}
+
+impl Drop for WaitForCancellation<'_> {
+  fn drop(&mut self) {
+    if self.key.is_some() {
+      self.shared_state.lock().waiters.remove(&mut self.key);
+    };
+  }
+}
+
+/// Cancels its `CancellationSignal` when dropped, unless it was disarmed first. Returned by `CancellationSignal::drop_guard`.
+pub struct CancellationDropGuard {
+  // Only `None` once disarmed.
+  signal: Option<CancellationSignal>,
+}
+
+impl CancellationDropGuard {
+  /// Returns the token without cancelling it.
+  pub fn disarm(mut self) -> CancellationSignal {
+    self.signal.take().unwrap()
+  }
+}
+
+impl Drop for CancellationDropGuard {
+  fn drop(&mut self) {
+    if let Some(signal) = &self.signal {
+      signal.cancel();
+    };
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  extern crate std;
+
+  use super::CancellationSignal;
+  use alloc::vec::Vec;
+  use futures::executor::block_on;
+  use std::thread;
+
+  #[test]
+  fn parent_cancels_descendants() {
+    let root = CancellationSignal::new();
+    let child = root.child();
+    let grandchild = child.child();
+    let waiter = thread::spawn(move || block_on(grandchild.cancelled()));
+    root.cancel();
+    waiter.join().unwrap();
+    assert!(child.is_cancelled());
+  }
+
+  #[test]
+  fn child_leaves_parent_untouched() {
+    let root = CancellationSignal::new();
+    let child = root.child();
+    let sibling = root.child();
+    child.cancel();
+    assert!(child.is_cancelled());
+    assert!(!root.is_cancelled());
+    assert!(!sibling.is_cancelled());
+  }
+
+  #[test]
+  fn child_of_cancelled_parent_is_cancelled() {
+    let root = CancellationSignal::new();
+    root.cancel();
+    let child = root.child();
+    assert!(child.is_cancelled());
+    block_on(child.cancelled());
+  }
+
+  #[test]
+  fn drop_guard_cancels_unless_disarmed() {
+    let token = CancellationSignal::new();
+    drop(token.clone().drop_guard());
+    assert!(token.is_cancelled());
+
+    let token = CancellationSignal::new();
+    let disarmed = token.clone().drop_guard().disarm();
+    assert!(!token.is_cancelled());
+    assert!(!disarmed.is_cancelled());
+  }
+
+  #[test]
+  fn prunes_dropped_children() {
+    let root = CancellationSignal::new();
+    let live: Vec<_> = (0..100).map(|_| root.child()).collect();
+    for _ in 0..1000 {
+      drop(root.child());
+    }
+    assert!(root.shared_state.lock().children.len() <= 2 * live.len() + 1);
+    root.cancel();
+    assert!(live.iter().all(|c| c.is_cancelled()));
+  }
+}
@@ -0,0 +1,222 @@
+use crate::sync::Arc;
+use crate::sync::Mutex;
+use crate::Canceled;
+use crate::SignalError;
+use alloc::vec::Vec;
+use core::future::Future;
+use core::mem::take;
+use core::pin::Pin;
+use core::task::Context;
+use core::task::Poll;
+use core::task::Waker;
+
+struct CountdownState<T> {
+  remaining: usize,
+  // Values signaled so far, in arrival order.
+  values: Vec<T>,
+  waker: Option<Waker>,
+  controllers: usize,
+  closed: bool,
+}
+
+/// Counts down the `CountdownSignal` it was created with. It is cheap to clone, so each worker can be handed its own; once every clone has been dropped before the count reaches zero, the future resolves with `Err(Canceled)`.
+pub struct CountdownController<T = ()> {
+  shared_state: Arc<Mutex<CountdownState<T>>>,
+}
+
+impl<T> Clone for CountdownController<T> {
+  fn clone(&self) -> Self {
+    self.shared_state.lock().controllers += 1;
+    CountdownController {
+      shared_state: self.shared_state.clone(),
+    }
+  }
+}
+
+impl<T> Drop for CountdownController<T> {
+  fn drop(&mut self) {
+    let waker = {
+      let mut shared_state = self.shared_state.lock();
+      shared_state.controllers -= 1;
+      if shared_state.controllers == 0 {
+        shared_state.waker.take()
+      } else {
+        None
+      }
+    };
+    if let Some(waker) = waker {
+      waker.wake();
+    };
+  }
+}
+
+impl<T> CountdownController<T> {
+  /// Returns how many more signals are needed before the future resolves.
+  pub fn remaining(&self) -> usize {
+    self.shared_state.lock().remaining
+  }
+
+  /// Returns whether the `CountdownSignal` has been dropped.
+  pub fn is_closed(&self) -> bool {
+    self.shared_state.lock().closed
+  }
+
+  /// Records `value` and decrements the count. Signals beyond the initial count, or after the `CountdownSignal` was dropped, are discarded.
+  pub fn signal(&self, value: T) {
+    let _ = self.try_signal(value);
+  }
+
+  /// Like `signal`, but hands the value back if the `CountdownSignal` was dropped or the count has already reached zero.
+  pub fn try_signal(&self, value: T) -> Result<(), SignalError<T>> {
+    let waker = {
+      let mut shared_state = self.shared_state.lock();
+      if shared_state.closed {
+        return Err(SignalError::Closed(value));
+      };
+      if shared_state.remaining == 0 {
+        return Err(SignalError::AlreadySignaled(value));
+      };
+      shared_state.values.push(value);
+      shared_state.remaining -= 1;
+      if shared_state.remaining == 0 {
+        shared_state.waker.take()
+      } else {
+        None
+      }
+    };
+    if let Some(waker) = waker {
+      waker.wake();
+    };
+    Ok(())
+  }
+}
+
+impl CountdownController {
+  /// Decrements the count, for latches that don't collect values.
+  pub fn count_down(&self) {
+    self.signal(());
+  }
+}
+
+/// A latch that resolves once it has been signaled a fixed number of times, e.g. to resume after N fan-out workers have finished. Each signal can carry a value, and the future resolves with all of them in the order they arrived.
+pub struct CountdownSignal<T = ()> {
+  shared_state: Arc<Mutex<CountdownState<T>>>,
+  terminated: bool,
+}
+
+impl<T> CountdownSignal<T> {
+  /// Creates a latch that resolves after `count` signals. A count of zero resolves immediately with no values.
+  pub fn new(count: usize) -> (CountdownSignal<T>, CountdownController<T>) {
+    let shared_state = Arc::new(Mutex::new(CountdownState {
+      remaining: count,
+      values: Vec::new(),
+      waker: None,
+      controllers: 1,
+      closed: false,
+    }));
+
+    (
+      CountdownSignal {
+        shared_state: shared_state.clone(),
+        terminated: false,
+      },
+      CountdownController {
+        shared_state: shared_state.clone(),
+      },
+    )
+  }
+
+  /// Returns how many more signals are needed before this resolves.
+  pub fn remaining(&self) -> usize {
+    self.shared_state.lock().remaining
+  }
+}
+
+impl<T> Future for CountdownSignal<T> {
+  type Output = Result<Vec<T>, Canceled>;
+
+  fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+    let this = self.get_mut();
+    assert!(
+      !this.terminated,
+      "`CountdownSignal` polled after completion"
+    );
+    let mut shared_state = this.shared_state.lock();
+    let res = if shared_state.remaining == 0 {
+      Ok(take(&mut shared_state.values))
+    } else if shared_state.controllers == 0 {
+      Err(Canceled)
+    } else {
+      shared_state.waker = Some(cx.waker().clone());
+      return Poll::Pending;
+    };
+    this.terminated = true;
+    Poll::Ready(res)
+  }
+}
+
+#[cfg(feature = "futures-core")]
+impl<T> futures_core::FusedFuture for CountdownSignal<T> {
+  fn is_terminated(&self) -> bool {
+    self.terminated
+  }
+}
+
+impl<T> Drop for CountdownSignal<T> {
+  fn drop(&mut self) {
+    self.shared_state.lock().closed = true;
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  extern crate std;
+
+  use super::CountdownSignal;
+  use crate::Canceled;
+  use crate::SignalError;
+  use alloc::vec;
+  use alloc::vec::Vec;
+  use futures::executor::block_on;
+  use std::thread;
+
+  #[test]
+  fn resolves_with_values_in_arrival_order() {
+    let (latch, ctl) = CountdownSignal::new(3);
+    for i in 0..3 {
+      let ctl = ctl.clone();
+      thread::spawn(move || ctl.signal(i)).join().unwrap();
+    }
+    assert_eq!(ctl.remaining(), 0);
+    assert_eq!(block_on(latch), Ok(vec![0, 1, 2]));
+  }
+
+  #[test]
+  fn zero_count_resolves_immediately() {
+    let (latch, ctl) = CountdownSignal::<u32>::new(0);
+    assert_eq!(latch.remaining(), 0);
+    assert_eq!(block_on(latch), Ok(Vec::new()));
+    drop(ctl);
+  }
+
+  #[test]
+  fn canceled_once_controllers_are_dropped_early() {
+    let (latch, ctl) = CountdownSignal::new(3);
+    let workers: Vec<_> = (0..2).map(|_| ctl.clone()).collect();
+    drop(ctl);
+    for (i, worker) in workers.into_iter().enumerate() {
+      worker.signal(i);
+    }
+    assert_eq!(block_on(latch), Err(Canceled));
+  }
+
+  #[test]
+  fn try_signal_after_count_reaches_zero() {
+    let (latch, ctl) = CountdownSignal::new(1);
+    assert_eq!(ctl.try_signal(1), Ok(()));
+    assert_eq!(ctl.try_signal(2), Err(SignalError::AlreadySignaled(2)));
+    assert_eq!(block_on(latch), Ok(vec![1]));
+    assert!(ctl.is_closed());
+    assert_eq!(ctl.try_signal(3), Err(SignalError::Closed(3)));
+  }
+}
@@ -0,0 +1,345 @@
+use crate::sync::Arc;
+use crate::sync::Mutex;
+use crate::waiters::Waiters;
+use crate::Canceled;
+use alloc::vec::Vec;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::Context;
+use core::task::Poll;
+
+struct EventState {
+  // Whether a waiter released by `set` takes the set state with it, leaving the event reset.
+  auto_reset: bool,
+  set: bool,
+  controllers: usize,
+  // A waiter is released by removing it from this list, so a waiter whose key is missing has been released even if the event has been reset since.
+  waiters: Waiters,
+}
+
+impl EventState {
+  fn new(auto_reset: bool, set: bool) -> Arc<Mutex<EventState>> {
+    Arc::new(Mutex::new(EventState {
+      auto_reset,
+      set,
+      controllers: 1,
+      waiters: Waiters::new(),
+    }))
+  }
+}
+
+fn add_controller(shared_state: &Mutex<EventState>) {
+  shared_state.lock().controllers += 1;
+}
+
+fn release_controller(shared_state: &Mutex<EventState>) {
+  let wakers = {
+    let mut shared_state = shared_state.lock();
+    shared_state.controllers -= 1;
+    if shared_state.controllers == 0 {
+      // Waiters stay registered, so they can tell they were woken to be canceled rather than released.
+      shared_state.waiters.wakers()
+    } else {
+      Vec::new()
+    }
+  };
+  for waker in wakers {
+    waker.wake();
+  }
+}
+
+/// Future returned by `AutoResetEvent::wait` and `ManualResetEvent::wait`. It resolves with `Err(Canceled)` if every controller is dropped while it is waiting.
+pub struct EventWait<'a> {
+  shared_state: &'a Mutex<EventState>,
+  key: Option<u64>,
+  terminated: bool,
+}
+
+impl Future for EventWait<'_> {
+  type Output = Result<(), Canceled>;
+
+  fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+    let this = self.get_mut();
+    assert!(!this.terminated, "`EventWait` polled after completion");
+    let mut shared_state = this.shared_state.lock();
+    let res = if this.key.is_some_and(|k| !shared_state.waiters.contains(k)) {
+      this.key = None;
+      Ok(())
+    } else if shared_state.set {
+      if shared_state.auto_reset {
+        shared_state.set = false;
+      };
+      shared_state.waiters.remove(&mut this.key);
+      Ok(())
+    } else if shared_state.controllers == 0 {
+      shared_state.waiters.remove(&mut this.key);
+      Err(Canceled)
+    } else {
+      shared_state.waiters.register(&mut this.key, cx.waker());
+      return Poll::Pending;
+    };
+    this.terminated = true;
+    Poll::Ready(res)
+  }
+}
+
+#[cfg(feature = "futures-core")]
+impl futures_core::FusedFuture for EventWait<'_> {
+  fn is_terminated(&self) -> bool {
+    self.terminated
+  }
+}
+
+impl Drop for EventWait<'_> {
+  fn drop(&mut self) {
+    let Some(key) = self.key else {
+      return;
+    };
+    let waker = {
+      let mut shared_state = self.shared_state.lock();
+      if shared_state.waiters.contains(key) {
+        shared_state.waiters.remove(&mut self.key);
+        None
+      } else if shared_state.auto_reset {
+        // This waiter was released but dropped before it could observe that, so pass the release on rather than lose it.
+        let next = shared_state.waiters.take_first();
+        if next.is_none() {
+          shared_state.set = true;
+        };
+        next
+      } else {
+        None
+      }
+    };
+    if let Some(waker) = waker {
+      waker.wake();
+    };
+  }
+}
+
+/// Controls the `AutoResetEvent` it was created with. It is cheap to clone; once every clone has been dropped, pending waits resolve with `Err(Canceled)`.
+pub struct AutoResetEventController {
+  shared_state: Arc<Mutex<EventState>>,
+}
+
+impl Clone for AutoResetEventController {
+  fn clone(&self) -> Self {
+    add_controller(&self.shared_state);
+    AutoResetEventController {
+      shared_state: self.shared_state.clone(),
+    }
+  }
+}
+
+impl Drop for AutoResetEventController {
+  fn drop(&mut self) {
+    release_controller(&self.shared_state);
+  }
+}
+
+impl AutoResetEventController {
+  /// Releases exactly one waiter, the one that has been waiting longest. If nobody is waiting, the event stays set until the next `wait` consumes it; setting an already set event has no further effect.
+  pub fn set(&self) {
+    let waker = {
+      let mut shared_state = self.shared_state.lock();
+      let next = shared_state.waiters.take_first();
+      if next.is_none() {
+        shared_state.set = true;
+      };
+      next
+    };
+    if let Some(waker) = waker {
+      waker.wake();
+    };
+  }
+}
+
+/// An async event that releases one waiter each time it is set, then resets itself. Unlike creating a new `SignalFuture` pair per occurrence, the same event can be waited on and set repeatedly, which suits repeating "something changed" notifications where each occurrence should be handled once.
+#[derive(Clone)]
+pub struct AutoResetEvent {
+  shared_state: Arc<Mutex<EventState>>,
+}
+
+impl AutoResetEvent {
+  pub fn new() -> (AutoResetEvent, AutoResetEventController) {
+    let shared_state = EventState::new(true, false);
+
+    (
+      AutoResetEvent {
+        shared_state: shared_state.clone(),
+      },
+      AutoResetEventController {
+        shared_state: shared_state.clone(),
+      },
+    )
+  }
+
+  /// Waits until the event is set, consuming the set state. Waiters are released in the order they started waiting.
+  pub fn wait(&self) -> EventWait<'_> {
+    EventWait {
+      shared_state: &self.shared_state,
+      key: None,
+      terminated: false,
+    }
+  }
+}
+
+/// Controls the `ManualResetEvent` it was created with. It is cheap to clone; once every clone has been dropped, pending waits on an event that is not set resolve with `Err(Canceled)`.
+pub struct ManualResetEventController {
+  shared_state: Arc<Mutex<EventState>>,
+}
+
+impl Clone for ManualResetEventController {
+  fn clone(&self) -> Self {
+    add_controller(&self.shared_state);
+    ManualResetEventController {
+      shared_state: self.shared_state.clone(),
+    }
+  }
+}
+
+impl Drop for ManualResetEventController {
+  fn drop(&mut self) {
+    release_controller(&self.shared_state);
+  }
+}
+
+impl ManualResetEventController {
+  /// Sets the event, releasing every current waiter. Until `reset` is called, waits complete immediately.
+  pub fn set(&self) {
+    let wakers = {
+      let mut shared_state = self.shared_state.lock();
+      shared_state.set = true;
+      shared_state.waiters.take_all()
+    };
+    for waker in wakers {
+      waker.wake();
+    }
+  }
+
+  /// Resets the event, so later waits block until it is set again. Waiters already released by `set` still complete.
+  pub fn reset(&self) {
+    self.shared_state.lock().set = false;
+  }
+
+  pub fn is_set(&self) -> bool {
+    self.shared_state.lock().set
+  }
+}
+
+/// An async event that stays set, releasing every waiter, until it is explicitly reset. This is the repeating counterpart of `SharedSignal` for state that can flip back and forth, such as "connection is up".
+#[derive(Clone)]
+pub struct ManualResetEvent {
+  shared_state: Arc<Mutex<EventState>>,
+}
+
+impl ManualResetEvent {
+  /// Creates an event that is initially set if `set` is true.
+  pub fn new(set: bool) -> (ManualResetEvent, ManualResetEventController) {
+    let shared_state = EventState::new(false, set);
+
+    (
+      ManualResetEvent {
+        shared_state: shared_state.clone(),
+      },
+      ManualResetEventController {
+        shared_state: shared_state.clone(),
+      },
+    )
+  }
+
+  /// Waits until the event is set. Completes immediately if it already is.
+  pub fn wait(&self) -> EventWait<'_> {
+    EventWait {
+      shared_state: &self.shared_state,
+      key: None,
+      terminated: false,
+    }
+  }
+
+  pub fn is_set(&self) -> bool {
+    self.shared_state.lock().set
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::AutoResetEvent;
+  use super::ManualResetEvent;
+  use crate::test_util::poll;
+  use crate::Canceled;
+  use core::task::Poll;
+
+  #[test]
+  fn auto_reset_releases_exactly_one_waiter() {
+    let (event, ctl) = AutoResetEvent::new();
+    let mut a = event.wait();
+    let mut b = event.wait();
+    assert!(poll(&mut a).is_pending());
+    assert!(poll(&mut b).is_pending());
+    ctl.set();
+    assert_eq!(poll(&mut a), Poll::Ready(Ok(())));
+    assert!(poll(&mut b).is_pending());
+    // The release was consumed, so a new waiter blocks too.
+    assert!(poll(&mut event.wait()).is_pending());
+    ctl.set();
+    assert_eq!(poll(&mut b), Poll::Ready(Ok(())));
+  }
+
+  #[test]
+  fn auto_reset_stays_set_until_waited_on() {
+    let (event, ctl) = AutoResetEvent::new();
+    ctl.set();
+    ctl.set();
+    assert_eq!(poll(&mut event.wait()), Poll::Ready(Ok(())));
+    assert!(poll(&mut event.wait()).is_pending());
+  }
+
+  #[test]
+  fn dropped_released_waiter_passes_release_on() {
+    let (event, ctl) = AutoResetEvent::new();
+    let mut a = event.wait();
+    let mut b = event.wait();
+    assert!(poll(&mut a).is_pending());
+    assert!(poll(&mut b).is_pending());
+    ctl.set();
+    drop(a);
+    assert_eq!(poll(&mut b), Poll::Ready(Ok(())));
+  }
+
+  #[test]
+  fn dropped_released_last_waiter_leaves_event_set() {
+    let (event, ctl) = AutoResetEvent::new();
+    let mut a = event.wait();
+    assert!(poll(&mut a).is_pending());
+    ctl.set();
+    drop(a);
+    assert_eq!(poll(&mut event.wait()), Poll::Ready(Ok(())));
+  }
+
+  #[test]
+  fn manual_reset_releases_every_waiter() {
+    let (event, ctl) = ManualResetEvent::new(false);
+    let mut a = event.wait();
+    let mut b = event.wait();
+    assert!(poll(&mut a).is_pending());
+    assert!(poll(&mut b).is_pending());
+    ctl.set();
+    ctl.reset();
+    // Both were released before the reset.
+    assert_eq!(poll(&mut a), Poll::Ready(Ok(())));
+    assert_eq!(poll(&mut b), Poll::Ready(Ok(())));
+    assert!(poll(&mut event.wait()).is_pending());
+  }
+
+  #[test]
+  fn dropping_controllers_cancels_waiters() {
+    let (event, ctl) = AutoResetEvent::new();
+    let mut a = event.wait();
+    assert!(poll(&mut a).is_pending());
+    drop(ctl.clone());
+    assert!(poll(&mut a).is_pending());
+    drop(ctl);
+    assert_eq!(poll(&mut a), Poll::Ready(Err(Canceled)));
+  }
+}
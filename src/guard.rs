@@ -0,0 +1,34 @@
+use crate::SignalFutureController;
+
+/// Signals a fallback value when dropped, unless `complete` was called first. Returned by `SignalFutureController::guard`, so early returns, `?` and panics in a handler still resolve the future rather than leaving it to be canceled.
+pub struct SignalGuard<T = ()> {
+  ctl: SignalFutureController<T>,
+  // Only `None` once `complete` has been called.
+  default: Option<T>,
+}
+
+impl<T> SignalGuard<T> {
+  /// Signals `value` instead of the fallback.
+  pub fn complete(mut self, value: T) {
+    self.default = None;
+    self.ctl.signal(value);
+  }
+}
+
+impl<T> Drop for SignalGuard<T> {
+  fn drop(&mut self) {
+    if let Some(default) = self.default.take() {
+      self.ctl.signal(default);
+    };
+  }
+}
+
+impl<T> SignalFutureController<T> {
+  /// Wraps this controller in a guard that signals `default` when dropped, unless `SignalGuard::complete` is called with the real value first.
+  pub fn guard(self, default: T) -> SignalGuard<T> {
+    SignalGuard {
+      ctl: self,
+      default: Some(default),
+    }
+  }
+}
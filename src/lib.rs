@@ -1,42 +1,481 @@
-use parking_lot::Mutex;
-use std::future::Future;
-use std::pin::Pin;
-use std::sync::Arc;
-use std::task::Context;
-use std::task::Poll;
-use std::task::Waker;
+#![cfg_attr(not(feature = "std"), no_std)]
 
+extern crate alloc;
+
+mod atomic_waker;
+mod batch;
+#[cfg(feature = "std")]
+mod blocking;
+mod callback;
+mod cancel;
+mod countdown;
+mod event;
+mod guard;
+mod local;
+mod map;
+#[cfg(feature = "std")]
+mod registry;
+mod set;
+mod shared;
+mod stream;
+mod sync;
+#[cfg(test)]
+mod test_util;
+#[cfg(feature = "std")]
+mod token;
+mod try_signal;
+mod waiters;
+mod watch;
+
+use crate::atomic_waker::AtomicWaker;
+pub use crate::batch::BatchRunner;
+pub use crate::batch::Batcher;
+#[cfg(feature = "std")]
+pub use crate::blocking::WaitTimeoutError;
+pub use crate::cancel::CancellationDropGuard;
+pub use crate::cancel::CancellationSignal;
+pub use crate::cancel::WaitForCancellation;
+pub use crate::countdown::CountdownController;
+pub use crate::countdown::CountdownSignal;
+pub use crate::event::AutoResetEvent;
+pub use crate::event::AutoResetEventController;
+pub use crate::event::EventWait;
+pub use crate::event::ManualResetEvent;
+pub use crate::event::ManualResetEventController;
+pub use crate::guard::SignalGuard;
+pub use crate::local::LocalClosed;
+pub use crate::local::LocalSignalFuture;
+pub use crate::local::LocalSignalFutureController;
+pub use crate::map::Map;
+pub use crate::map::MapInput;
+#[cfg(feature = "std")]
+pub use crate::registry::DuplicateKey;
+#[cfg(feature = "std")]
+pub use crate::registry::SignalRegistry;
+pub use crate::set::NextCompleted;
+pub use crate::set::SignalSet;
+pub use crate::shared::SharedSignal;
+pub use crate::shared::SharedSignalController;
+pub use crate::shared::SharedSignalWait;
+pub use crate::stream::SignalStream;
+pub use crate::stream::SignalStreamController;
+pub use crate::stream::SignalStreamRecv;
+pub use crate::stream::SignalStreamSend;
+pub use crate::stream::TrySendError;
+use crate::sync::spin_loop;
+use crate::sync::Arc;
+use crate::sync::AtomicUsize;
+use crate::sync::Mutex;
+use crate::sync::UnsafeCell;
+#[cfg(feature = "std")]
+pub use crate::token::SignalToken;
+#[cfg(feature = "std")]
+pub use crate::token::TokenRegistry;
+#[cfg(feature = "std")]
+pub use crate::try_signal::catch_signal;
+pub use crate::try_signal::TrySignalError;
+pub use crate::try_signal::TrySignalFuture;
+pub use crate::try_signal::TrySignalFutureController;
+pub use crate::watch::WatchChanged;
+pub use crate::watch::WatchRef;
+pub use crate::watch::WatchSignal;
+pub use crate::watch::WatchSignalController;
+use alloc::vec::Vec;
+use core::fmt;
+use core::fmt::Display;
+use core::future::Future;
+use core::mem::take;
+use core::pin::Pin;
+use core::sync::atomic::Ordering::AcqRel;
+use core::sync::atomic::Ordering::Acquire;
+use core::sync::atomic::Ordering::Relaxed;
+use core::sync::atomic::Ordering::Release;
+use core::task::Context;
+use core::task::Poll;
+use core::task::Waker;
+
+// Bits of `State::flags`. The bits above these count the live controllers; once that count reaches zero without a value, the future can never complete, so it resolves with `Canceled` instead.
+// A thread is moving a value into or out of `State::value`, and nobody else may touch the slot until it clears this bit. It is only held for the duration of a move.
+const LOCKED: usize = 1 << 0;
+// `State::value` holds a signaled value that the `SignalFuture` hasn't received yet.
+const SIGNALED: usize = 1 << 1;
+// The `SignalFuture` has received the signaled value.
+const CONSUMED: usize = 1 << 2;
+// The `SignalFuture` has been dropped, at which point nothing can observe a signaled value.
+const CLOSED: usize = 1 << 3;
+const CONTROLLER: usize = 1 << 4;
+
+// Shared between a `SignalFuture` and its controllers. Signaling and polling only touch `flags` and `waker`, which are atomics, so neither takes a lock on the hot path.
 struct State<T = ()> {
+  flags: AtomicUsize,
+  value: UnsafeCell<Option<T>>,
+  waker: AtomicWaker,
+  // Tasks waiting in `SignalFutureController::closed`. There can be several, as the controller is cloneable, and this is rarely used so a lock is fine.
+  closed_wakers: Mutex<Vec<Waker>>,
+  policy: SignalPolicy,
+}
+
+// `value` is only accessed by the thread holding the LOCKED bit, so sharing `State` only ever moves a `T` between threads.
+unsafe impl<T: Send> Send for State<T> {}
+unsafe impl<T: Send> Sync for State<T> {}
+
+impl<T> State<T> {
+  fn new(policy: SignalPolicy) -> State<T> {
+    State {
+      flags: AtomicUsize::new(CONTROLLER),
+      value: UnsafeCell::new(None),
+      waker: AtomicWaker::new(),
+      closed_wakers: Mutex::new(Vec::new()),
+      policy,
+    }
+  }
+
+  // Spins until this thread holds the LOCKED bit, and returns the flags at the time it was taken.
+  fn lock(&self) -> usize {
+    let mut flags = self.flags.load(Relaxed);
+    loop {
+      if flags & LOCKED != 0 {
+        spin_loop();
+        flags = self.flags.load(Relaxed);
+        continue;
+      };
+      match self
+        .flags
+        .compare_exchange_weak(flags, flags | LOCKED, Acquire, Relaxed)
+      {
+        Ok(_) => return flags,
+        Err(actual) => flags = actual,
+      };
+    }
+  }
+
+  // Releases the LOCKED bit, setting and clearing `set` and `clear` in the same step. Other bits may change concurrently, so this can't be a plain store.
+  fn unlock(&self, set: usize, clear: usize) {
+    let mut flags = self.flags.load(Relaxed);
+    loop {
+      let new_flags = (flags | set) & !(clear | LOCKED);
+      match self
+        .flags
+        .compare_exchange_weak(flags, new_flags, Release, Relaxed)
+      {
+        Ok(_) => return,
+        Err(actual) => flags = actual,
+      };
+    }
+  }
+
+  fn is_closed(&self) -> bool {
+    self.flags.load(Acquire) & CLOSED != 0
+  }
+
+  fn is_signaled(&self) -> bool {
+    self.flags.load(Acquire) & SIGNALED != 0
+  }
+
+  // Only the receiving side may call this, as it moves the value out of the slot while `f` runs. That way the LOCKED bit is only held for two moves rather than for as long as `f`, which is user code.
+  fn peek<R>(&self, f: impl FnOnce(&T) -> R) -> Option<R> {
+    if !self.is_signaled() {
+      return None;
+    };
+    self.lock();
+    // SAFETY: We hold the LOCKED bit.
+    let value = self.value.with_mut(|slot| unsafe { (*slot).take() });
+    self.unlock(0, 0);
+    // Puts the value back once `f` returns, or if it panics.
+    let peeked = Peeked { state: self, value };
+    peeked.value.as_ref().map(f)
+  }
+
+  // Stores `value` for the future to receive. A pending value is only replaced if `replace` is set.
+  fn store(&self, value: T, replace: bool) -> Result<(), SignalError<T>> {
+    let flags = self.lock();
+    if flags & CLOSED != 0 {
+      self.unlock(0, 0);
+      return Err(SignalError::Closed(value));
+    };
+    if flags & CONSUMED != 0 || (flags & SIGNALED != 0 && !replace) {
+      self.unlock(0, 0);
+      return Err(SignalError::AlreadySignaled(value));
+    };
+    // SAFETY: We hold the LOCKED bit.
+    let replaced = self
+      .value
+      .with_mut(|slot| unsafe { (*slot).replace(value) });
+    self.unlock(SIGNALED, 0);
+    self.waker.wake();
+    // Drop any replaced value only after releasing the slot, as its destructor could be arbitrarily slow.
+    drop(replaced);
+    Ok(())
+  }
+
+  // Returns the outcome if the future can resolve now, without registering for a wakeup.
+  fn take(&self) -> Option<Result<T, Canceled>> {
+    let flags = self.flags.load(Acquire);
+    if flags & SIGNALED != 0 {
+      self.lock();
+      // SAFETY: We hold the LOCKED bit. Only the receiving side clears SIGNALED, so the slot is still filled.
+      let value = self.value.with_mut(|slot| unsafe { (*slot).take() });
+      self.unlock(CONSUMED, SIGNALED);
+      value.map(Ok)
+    } else if flags < CONTROLLER {
+      // No controllers are left to signal, and the flags were read in one load so a value can't have arrived in between.
+      Some(Err(Canceled))
+    } else {
+      None
+    }
+  }
+
+  fn poll(&self, waker: &Waker) -> Poll<Result<T, Canceled>> {
+    if let Some(res) = self.take() {
+      return Poll::Ready(res);
+    };
+    self.waker.register(waker);
+    // Check again in case a controller signaled or dropped before the waker was registered.
+    match self.take() {
+      Some(res) => Poll::Ready(res),
+      None => Poll::Pending,
+    }
+  }
+
+  fn add_controller(&self) {
+    self.flags.fetch_add(CONTROLLER, Relaxed);
+  }
+
+  fn release_controller(&self) {
+    if self.flags.fetch_sub(CONTROLLER, AcqRel) < CONTROLLER * 2 {
+      self.waker.wake();
+    };
+  }
+
+  fn close(&self) {
+    self.flags.fetch_or(CLOSED, AcqRel);
+    let wakers = take(&mut *self.closed_wakers.lock());
+    for waker in wakers {
+      waker.wake();
+    }
+  }
+
+  fn poll_closed(&self, waker: &Waker) -> Poll<()> {
+    if self.is_closed() {
+      return Poll::Ready(());
+    };
+    let mut closed_wakers = self.closed_wakers.lock();
+    // `close` sets the flag before taking the lock, so checking again while holding it guarantees this waker is either seen by `close` or unnecessary.
+    if self.is_closed() {
+      return Poll::Ready(());
+    };
+    if !closed_wakers.iter().any(|w| w.will_wake(waker)) {
+      closed_wakers.push(waker.clone());
+    };
+    Poll::Pending
+  }
+}
+
+// A value moved out of `State::value` by `State::peek`, which is put back when dropped. Until then SIGNALED stays set, so controllers act as if the value were still in the slot: `try_signal` fails, and a `LastWins` signal fills the slot, in which case the newer value is kept.
+struct Peeked<'a, T> {
+  state: &'a State<T>,
   value: Option<T>,
-  waker: Option<Waker>,
 }
 
-#[derive(Clone)]
+impl<T> Drop for Peeked<'_, T> {
+  fn drop(&mut self) {
+    self.state.lock();
+    // SAFETY: We hold the LOCKED bit.
+    let replaced = self.state.value.with_mut(|slot| unsafe {
+      if (*slot).is_none() {
+        *slot = self.value.take();
+      };
+      self.value.take()
+    });
+    self.state.unlock(0, 0);
+    drop(replaced);
+  }
+}
+
+/// Decides what `SignalFutureController::signal` does when a value has already been signaled but not yet received, e.g. when several cloned controllers race to complete the same future.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SignalPolicy {
+  /// The earliest value is kept and later ones are dropped.
+  FirstWins,
+  /// Each value replaces the pending one, so the future receives the most recent value.
+  #[default]
+  LastWins,
+}
+
+/// Error returned by a `SignalFuture` when every `SignalFutureController` for it was dropped without signaling a value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Canceled;
+
+impl Display for Canceled {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "signal future canceled")
+  }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Canceled {}
+
+/// Error returned by `SignalFutureController::try_signal` and the other controllers' fallible signaling methods when the value could not be delivered. The undelivered value is handed back so it can be retried, rerouted, or logged.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SignalError<T> {
+  /// The receiving side was dropped or closed, so nothing will receive the value.
+  Closed(T),
+  /// The future doesn't take another value: one is already waiting to be received and the signal doesn't replace it, one has already been received, or, for a `CountdownController`, the count has already reached zero.
+  AlreadySignaled(T),
+}
+
+impl<T> SignalError<T> {
+  /// Returns the value that could not be delivered.
+  pub fn into_inner(self) -> T {
+    match self {
+      SignalError::Closed(v) => v,
+      SignalError::AlreadySignaled(v) => v,
+    }
+  }
+}
+
+impl<T> Display for SignalError<T> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      SignalError::Closed(_) => write!(f, "signal receiver was dropped or closed"),
+      SignalError::AlreadySignaled(_) => write!(f, "signal future was already signaled"),
+    }
+  }
+}
+
+#[cfg(feature = "std")]
+impl<T: fmt::Debug> std::error::Error for SignalError<T> {}
+
+/// Error returned by `SignalFuture::try_take` when there is no value to take.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TryTakeError {
+  /// Nothing has been signaled yet.
+  Pending,
+  /// Every controller was dropped without signaling.
+  Canceled,
+}
+
+impl From<Canceled> for TryTakeError {
+  fn from(_: Canceled) -> Self {
+    TryTakeError::Canceled
+  }
+}
+
+impl Display for TryTakeError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      TryTakeError::Pending => write!(f, "signal future is still pending"),
+      TryTakeError::Canceled => Display::fmt(&Canceled, f),
+    }
+  }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for TryTakeError {}
+
+/// Resolves the `SignalFuture` it was created with. Clones control the same future; once every clone has been dropped without calling `signal`, the future resolves with `Err(Canceled)`. If more than one value is signaled, the `SignalPolicy` chosen when creating the future decides which one is received; use `OnceController` instead to rule out signaling twice entirely.
 pub struct SignalFutureController<T = ()> {
-  shared_state: Arc<Mutex<State<T>>>,
+  shared_state: Arc<State<T>>,
+}
+
+impl<T> Clone for SignalFutureController<T> {
+  fn clone(&self) -> Self {
+    self.shared_state.add_controller();
+    SignalFutureController {
+      shared_state: self.shared_state.clone(),
+    }
+  }
+}
+
+impl<T> Drop for SignalFutureController<T> {
+  fn drop(&mut self) {
+    self.shared_state.release_controller();
+  }
 }
 
 impl<T> SignalFutureController<T> {
+  /// Returns whether the `SignalFuture` has been dropped. Producers can use this to skip work for a request nobody is awaiting anymore.
+  pub fn is_closed(&self) -> bool {
+    self.shared_state.is_closed()
+  }
+
+  /// Returns a future that completes once the `SignalFuture` has been dropped. This is useful to abort work early, e.g. by racing it against the work in a `select!`.
+  pub fn closed(&self) -> Closed<'_, T> {
+    Closed {
+      shared_state: &self.shared_state,
+    }
+  }
+
   pub fn signal(&self, value: T) {
-    let mut shared_state = self.shared_state.lock();
-    shared_state.value = Some(value);
-    if let Some(waker) = shared_state.waker.take() {
-      waker.wake();
-    };
+    let replace = self.shared_state.policy == SignalPolicy::LastWins;
+    let _ = self.shared_state.store(value, replace);
+  }
+
+  /// Like `signal`, but reports when the value could not be delivered and hands it back: either the `SignalFuture` was dropped, or another value is already waiting to be received. Unlike `signal`, this never replaces a pending value.
+  pub fn try_signal(&self, value: T) -> Result<(), SignalError<T>> {
+    self.shared_state.store(value, false)
+  }
+}
+
+/// A controller that can signal at most once. It cannot be cloned and `signal` consumes it, so completing the future twice is a compile error rather than a silently replaced value. Dropping it without signaling resolves the future with `Err(Canceled)`.
+pub struct OnceController<T = ()> {
+  shared_state: Arc<State<T>>,
+}
+
+impl<T> Drop for OnceController<T> {
+  fn drop(&mut self) {
+    self.shared_state.release_controller();
+  }
+}
+
+impl<T> OnceController<T> {
+  /// Returns whether the `SignalFuture` has been dropped.
+  pub fn is_closed(&self) -> bool {
+    self.shared_state.is_closed()
+  }
+
+  /// Returns a future that completes once the `SignalFuture` has been dropped.
+  pub fn closed(&self) -> Closed<'_, T> {
+    Closed {
+      shared_state: &self.shared_state,
+    }
+  }
+
+  /// Resolves the future with `value`. If the `SignalFuture` was already dropped, the value is handed back.
+  pub fn signal(self, value: T) -> Result<(), T> {
+    self
+      .shared_state
+      .store(value, false)
+      .map_err(SignalError::into_inner)
+  }
+}
+
+/// Future returned by `SignalFutureController::closed` and `OnceController::closed`.
+pub struct Closed<'a, T = ()> {
+  shared_state: &'a State<T>,
+}
+
+impl<T> Future for Closed<'_, T> {
+  type Output = ();
+
+  fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+    self.shared_state.poll_closed(cx.waker())
   }
 }
 
 /// A simple future that can be programmatically resolved externally using the controller that is provided in tandem when creating a `SignalFuture`. This makes it useful as a way to signal to some consumer of the future that something has completed, using standard async syntax and semantics.
 ///
+/// The future resolves to `Ok(value)` once a controller signals, or to `Err(Canceled)` if every controller is dropped first, in the same way a oneshot channel's receiver does when its sender goes away. Polling it again after it has resolved panics; with the `futures-core` feature it implements `FusedFuture`, so `select!` loops can skip it once it has completed.
+///
 /// # Examples
 ///
-/// ```
+/// ```ignore
 /// struct DelayedWriter { fd: File, pending: Mutex<Vec<(u64, Vec<u8>, SignalFutureController)>> }
 /// impl DelayedWriter {
 ///   pub async fn write(&self, offset: u64, data: Vec<u8>) {
 ///     let (fut, fut_ctl) = SignalFuture::new();
 ///     self.pending.lock().await.push((offset, data, fut_ctl));
-///     fut.await
+///     fut.await.expect("background loop dropped the write")
 ///   }
 ///   pub async fn background_loop(&self) {
 ///     loop {
@@ -50,37 +489,185 @@ impl<T> SignalFutureController<T> {
 /// }
 /// ```
 pub struct SignalFuture<T = ()> {
-  shared_state: Arc<Mutex<State<T>>>,
+  shared_state: Arc<State<T>>,
+  terminated: bool,
 }
 
 impl<T> SignalFuture<T> {
   pub fn new() -> (SignalFuture<T>, SignalFutureController<T>) {
-    let shared_state = Arc::new(Mutex::new(State {
-      value: None,
-      waker: None,
-    }));
+    SignalFuture::with_policy(SignalPolicy::default())
+  }
+
+  /// Like `new`, but with a specific `SignalPolicy` for resolving repeated signals.
+  pub fn with_policy(policy: SignalPolicy) -> (SignalFuture<T>, SignalFutureController<T>) {
+    let shared_state = Arc::new(State::new(policy));
 
     (
       SignalFuture {
         shared_state: shared_state.clone(),
+        terminated: false,
       },
       SignalFutureController {
         shared_state: shared_state.clone(),
       },
     )
   }
+
+  fn poll_with(&mut self, waker: &Waker) -> Poll<Result<T, Canceled>> {
+    assert!(!self.terminated, "`SignalFuture` polled after completion");
+    let res = self.shared_state.poll(waker);
+    self.terminated = res.is_ready();
+    res
+  }
+
+  /// Takes the signaled value if there is one, without registering a waker or otherwise waiting. Once this returns a value or `TryTakeError::Canceled`, the future has completed and must not be polled or taken from again.
+  pub fn try_take(&mut self) -> Result<T, TryTakeError> {
+    assert!(
+      !self.terminated,
+      "`SignalFuture` taken from after completion"
+    );
+    let res = self.shared_state.take().ok_or(TryTakeError::Pending)?;
+    self.terminated = true;
+    Ok(res?)
+  }
+
+  /// Returns whether a value has been signaled and is waiting to be received.
+  pub fn is_signaled(&self) -> bool {
+    self.shared_state.is_signaled()
+  }
+
+  /// Calls `f` with a reference to the signaled value, if there is one, and returns its result. The value stays in place to be received later, even if `f` panics. Controllers aren't held off while `f` runs; if one signals under `SignalPolicy::LastWins` meanwhile, its value replaces the peeked one as usual.
+  pub fn peek<R>(&mut self, f: impl FnOnce(&T) -> R) -> Option<R> {
+    self.shared_state.peek(f)
+  }
+
+  /// Creates a future whose only controller is a single-shot `OnceController`.
+  pub fn new_once() -> (SignalFuture<T>, OnceController<T>) {
+    let shared_state = Arc::new(State::new(SignalPolicy::default()));
+
+    (
+      SignalFuture {
+        shared_state: shared_state.clone(),
+        terminated: false,
+      },
+      OnceController {
+        shared_state: shared_state.clone(),
+      },
+    )
+  }
 }
 
 impl<T> Future for SignalFuture<T> {
-  type Output = T;
+  type Output = Result<T, Canceled>;
 
   fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-    let mut shared_state = self.shared_state.lock();
-    if let Some(v) = shared_state.value.take() {
-      Poll::Ready(v)
-    } else {
-      shared_state.waker = Some(cx.waker().clone());
-      Poll::Pending
-    }
+    self.get_mut().poll_with(cx.waker())
+  }
+}
+
+#[cfg(feature = "futures-core")]
+impl<T> futures_core::FusedFuture for SignalFuture<T> {
+  fn is_terminated(&self) -> bool {
+    self.terminated
+  }
+}
+
+impl<T> Drop for SignalFuture<T> {
+  fn drop(&mut self) {
+    self.shared_state.close();
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  extern crate std;
+
+  use crate::test_util::poll;
+  use crate::Canceled;
+  use crate::SignalError;
+  use crate::SignalFuture;
+  use crate::SignalPolicy;
+  use core::task::Poll;
+  use futures::executor::block_on;
+  #[cfg(feature = "futures-core")]
+  use futures_core::FusedFuture;
+  use std::panic::catch_unwind;
+  use std::panic::AssertUnwindSafe;
+  use std::thread;
+
+  #[test]
+  fn signal_after_panicking_peek() {
+    let (mut fut, ctl) = SignalFuture::new();
+    ctl.signal(1);
+    let res = catch_unwind(AssertUnwindSafe(|| fut.peek(|_| panic!("peek"))));
+    assert!(res.is_err());
+    assert_eq!(fut.peek(|v| *v), Some(1));
+    thread::spawn(move || ctl.signal(2)).join().unwrap();
+    assert_eq!(fut.try_take(), Ok(2));
+  }
+
+  #[test]
+  fn signal_during_peek() {
+    let (mut fut, ctl) = SignalFuture::new();
+    ctl.signal(1);
+    // Signaling from another thread while `f` runs would spin forever if `f` ran with the slot locked.
+    let seen = fut.peek(|v| {
+      let ctl = ctl.clone();
+      thread::spawn(move || ctl.signal(2)).join().unwrap();
+      *v
+    });
+    assert_eq!(seen, Some(1));
+    assert_eq!(ctl.try_signal(3), Err(SignalError::AlreadySignaled(3)));
+    assert_eq!(fut.try_take(), Ok(2));
+  }
+
+  #[test]
+  fn policy_decides_which_value_is_received() {
+    let (fut, ctl) = SignalFuture::with_policy(SignalPolicy::FirstWins);
+    ctl.signal(1);
+    ctl.clone().signal(2);
+    assert_eq!(block_on(fut), Ok(1));
+
+    let (fut, ctl) = SignalFuture::with_policy(SignalPolicy::LastWins);
+    ctl.signal(1);
+    ctl.clone().signal(2);
+    assert_eq!(block_on(fut), Ok(2));
+  }
+
+  #[test]
+  fn once_controller_hands_value_back_after_future_is_dropped() {
+    let (fut, ctl) = SignalFuture::new_once();
+    assert!(!ctl.is_closed());
+    drop(fut);
+    assert!(ctl.is_closed());
+    assert_eq!(ctl.signal(1), Err(1));
+
+    let (fut, ctl) = SignalFuture::new_once();
+    assert_eq!(ctl.signal(1), Ok(()));
+    assert_eq!(block_on(fut), Ok(1));
+
+    let (fut, ctl) = SignalFuture::<u32>::new_once();
+    drop(ctl);
+    assert_eq!(block_on(fut), Err(Canceled));
+  }
+
+  #[cfg(feature = "futures-core")]
+  #[test]
+  fn is_terminated_once_ready() {
+    let (mut fut, ctl) = SignalFuture::new();
+    assert!(poll(&mut fut).is_pending());
+    assert!(!fut.is_terminated());
+    ctl.signal(1);
+    assert_eq!(poll(&mut fut), Poll::Ready(Ok(1)));
+    assert!(fut.is_terminated());
+  }
+
+  #[test]
+  #[should_panic(expected = "`SignalFuture` polled after completion")]
+  fn poll_after_completion_panics() {
+    let (mut fut, ctl) = SignalFuture::new();
+    ctl.signal(1);
+    assert_eq!(poll(&mut fut), Poll::Ready(Ok(1)));
+    let _ = poll(&mut fut);
   }
 }
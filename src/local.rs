@@ -0,0 +1,319 @@
+use crate::Canceled;
+use crate::SignalError;
+use crate::SignalPolicy;
+use crate::TryTakeError;
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use core::future::Future;
+use core::mem::take;
+use core::pin::Pin;
+use core::task::Context;
+use core::task::Poll;
+use core::task::Waker;
+
+struct LocalState<T> {
+  value: Option<T>,
+  // Set once the future has received the value, after which nothing more can be signaled.
+  consumed: bool,
+  waker: Option<Waker>,
+  controllers: usize,
+  closed: bool,
+  closed_wakers: Vec<Waker>,
+  policy: SignalPolicy,
+}
+
+impl<T> LocalState<T> {
+  // Returns the waker to wake and any replaced value to drop once the state is no longer borrowed, as waking or the value's destructor may run code that touches it again.
+  fn store(
+    &mut self,
+    value: T,
+    replace: bool,
+  ) -> Result<(Option<Waker>, Option<T>), SignalError<T>> {
+    if self.closed {
+      return Err(SignalError::Closed(value));
+    };
+    if self.consumed || (self.value.is_some() && !replace) {
+      return Err(SignalError::AlreadySignaled(value));
+    };
+    let replaced = self.value.replace(value);
+    Ok((self.waker.take(), replaced))
+  }
+}
+
+fn wake(waker: Option<Waker>) {
+  if let Some(waker) = waker {
+    waker.wake();
+  };
+}
+
+/// Single-threaded counterpart of `SignalFutureController`, for use with `LocalSignalFuture`. It is `!Send`, and in exchange cloning and signaling only touch a non-atomic reference count and a `RefCell`.
+pub struct LocalSignalFutureController<T = ()> {
+  shared_state: Rc<RefCell<LocalState<T>>>,
+}
+
+impl<T> Clone for LocalSignalFutureController<T> {
+  fn clone(&self) -> Self {
+    self.shared_state.borrow_mut().controllers += 1;
+    LocalSignalFutureController {
+      shared_state: self.shared_state.clone(),
+    }
+  }
+}
+
+impl<T> Drop for LocalSignalFutureController<T> {
+  fn drop(&mut self) {
+    let waker = {
+      let mut shared_state = self.shared_state.borrow_mut();
+      shared_state.controllers -= 1;
+      if shared_state.controllers == 0 {
+        shared_state.waker.take()
+      } else {
+        None
+      }
+    };
+    wake(waker);
+  }
+}
+
+impl<T> LocalSignalFutureController<T> {
+  /// Returns whether the `LocalSignalFuture` has been dropped.
+  pub fn is_closed(&self) -> bool {
+    self.shared_state.borrow().closed
+  }
+
+  /// Returns a future that completes once the `LocalSignalFuture` has been dropped.
+  pub fn closed(&self) -> LocalClosed<'_, T> {
+    LocalClosed {
+      shared_state: &self.shared_state,
+    }
+  }
+
+  pub fn signal(&self, value: T) {
+    let res = {
+      let mut shared_state = self.shared_state.borrow_mut();
+      let replace = shared_state.policy == SignalPolicy::LastWins;
+      shared_state.store(value, replace)
+    };
+    if let Ok((waker, replaced)) = res {
+      wake(waker);
+      drop(replaced);
+    };
+  }
+
+  /// Like `signal`, but hands the value back if the `LocalSignalFuture` was dropped or a value was already signaled. This never replaces a pending value.
+  pub fn try_signal(&self, value: T) -> Result<(), SignalError<T>> {
+    let (waker, replaced) = self.shared_state.borrow_mut().store(value, false)?;
+    wake(waker);
+    drop(replaced);
+    Ok(())
+  }
+}
+
+/// Future returned by `LocalSignalFutureController::closed`.
+pub struct LocalClosed<'a, T = ()> {
+  shared_state: &'a RefCell<LocalState<T>>,
+}
+
+impl<T> Future for LocalClosed<'_, T> {
+  type Output = ();
+
+  fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+    let mut shared_state = self.shared_state.borrow_mut();
+    if shared_state.closed {
+      return Poll::Ready(());
+    };
+    if !shared_state
+      .closed_wakers
+      .iter()
+      .any(|w| w.will_wake(cx.waker()))
+    {
+      shared_state.closed_wakers.push(cx.waker().clone());
+    };
+    Poll::Pending
+  }
+}
+
+/// Single-threaded counterpart of `SignalFuture`, for executors that run every task on one thread (e.g. a tokio `LocalSet` or a thread-per-core runtime). It has the same semantics as `SignalFuture`, including panicking if polled after completion, but is built on `Rc` and `RefCell` instead of atomics, so neither it nor its controllers can be sent to another thread.
+pub struct LocalSignalFuture<T = ()> {
+  shared_state: Rc<RefCell<LocalState<T>>>,
+  terminated: bool,
+}
+
+impl<T> LocalSignalFuture<T> {
+  pub fn new() -> (LocalSignalFuture<T>, LocalSignalFutureController<T>) {
+    LocalSignalFuture::with_policy(SignalPolicy::default())
+  }
+
+  /// Like `new`, but with a specific `SignalPolicy` for resolving repeated signals.
+  pub fn with_policy(
+    policy: SignalPolicy,
+  ) -> (LocalSignalFuture<T>, LocalSignalFutureController<T>) {
+    let shared_state = Rc::new(RefCell::new(LocalState {
+      value: None,
+      consumed: false,
+      waker: None,
+      controllers: 1,
+      closed: false,
+      closed_wakers: Vec::new(),
+      policy,
+    }));
+
+    (
+      LocalSignalFuture {
+        shared_state: shared_state.clone(),
+        terminated: false,
+      },
+      LocalSignalFutureController {
+        shared_state: shared_state.clone(),
+      },
+    )
+  }
+
+  /// Takes the signaled value if there is one, without registering a waker. See `SignalFuture::try_take`.
+  pub fn try_take(&mut self) -> Result<T, TryTakeError> {
+    assert!(
+      !self.terminated,
+      "`LocalSignalFuture` taken from after completion"
+    );
+    let mut shared_state = self.shared_state.borrow_mut();
+    if let Some(v) = shared_state.value.take() {
+      shared_state.consumed = true;
+      self.terminated = true;
+      Ok(v)
+    } else if shared_state.controllers == 0 {
+      self.terminated = true;
+      Err(TryTakeError::Canceled)
+    } else {
+      Err(TryTakeError::Pending)
+    }
+  }
+
+  /// Returns whether a value has been signaled and is waiting to be received.
+  pub fn is_signaled(&self) -> bool {
+    self.shared_state.borrow().value.is_some()
+  }
+
+  /// Calls `f` with a reference to the signaled value, if there is one, and returns its result. The value stays in place to be received later. `f` must not signal this future itself.
+  pub fn peek<R>(&self, f: impl FnOnce(&T) -> R) -> Option<R> {
+    self.shared_state.borrow().value.as_ref().map(f)
+  }
+}
+
+impl<T> Future for LocalSignalFuture<T> {
+  type Output = Result<T, Canceled>;
+
+  fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+    let this = self.get_mut();
+    assert!(
+      !this.terminated,
+      "`LocalSignalFuture` polled after completion"
+    );
+    let mut shared_state = this.shared_state.borrow_mut();
+    if let Some(v) = shared_state.value.take() {
+      shared_state.consumed = true;
+      this.terminated = true;
+      Poll::Ready(Ok(v))
+    } else if shared_state.controllers == 0 {
+      this.terminated = true;
+      Poll::Ready(Err(Canceled))
+    } else {
+      shared_state.waker = Some(cx.waker().clone());
+      Poll::Pending
+    }
+  }
+}
+
+#[cfg(feature = "futures-core")]
+impl<T> futures_core::FusedFuture for LocalSignalFuture<T> {
+  fn is_terminated(&self) -> bool {
+    self.terminated
+  }
+}
+
+impl<T> Drop for LocalSignalFuture<T> {
+  fn drop(&mut self) {
+    let wakers = {
+      let mut shared_state = self.shared_state.borrow_mut();
+      shared_state.closed = true;
+      take(&mut shared_state.closed_wakers)
+    };
+    for waker in wakers {
+      waker.wake();
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::LocalSignalFuture;
+  use super::LocalSignalFutureController;
+  use crate::Canceled;
+  use crate::SignalError;
+  use crate::SignalPolicy;
+  use crate::TryTakeError;
+  use futures::executor::block_on;
+  use futures::FutureExt;
+
+  // Touches the future's state when dropped.
+  struct Reentrant(LocalSignalFutureController<Reentrant>);
+
+  impl Drop for Reentrant {
+    fn drop(&mut self) {
+      let _ = self.0.is_closed();
+    }
+  }
+
+  #[test]
+  fn replaced_value_is_dropped_outside_borrow() {
+    let (mut fut, ctl) = LocalSignalFuture::new();
+    ctl.signal(Reentrant(ctl.clone()));
+    ctl.signal(Reentrant(ctl.clone()));
+    assert!(fut.try_take().is_ok());
+  }
+
+  #[test]
+  fn policy_decides_which_value_is_received() {
+    let (fut, ctl) = LocalSignalFuture::with_policy(SignalPolicy::FirstWins);
+    ctl.signal(1);
+    ctl.signal(2);
+    assert_eq!(block_on(fut), Ok(1));
+
+    let (fut, ctl) = LocalSignalFuture::with_policy(SignalPolicy::LastWins);
+    ctl.signal(1);
+    ctl.signal(2);
+    assert_eq!(block_on(fut), Ok(2));
+  }
+
+  #[test]
+  fn try_signal_hands_value_back() {
+    let (mut fut, ctl) = LocalSignalFuture::new();
+    assert_eq!(ctl.try_signal(1), Ok(()));
+    assert_eq!(ctl.try_signal(2), Err(SignalError::AlreadySignaled(2)));
+    assert_eq!(fut.peek(|v| *v), Some(1));
+    assert_eq!(fut.try_take(), Ok(1));
+    assert_eq!(ctl.try_signal(3), Err(SignalError::AlreadySignaled(3)));
+    drop(fut);
+    assert_eq!(ctl.try_signal(4), Err(SignalError::Closed(4)));
+  }
+
+  #[test]
+  fn canceled_once_controllers_are_dropped() {
+    let (mut fut, ctl) = LocalSignalFuture::<u32>::new();
+    let other = ctl.clone();
+    drop(ctl);
+    assert_eq!(fut.try_take(), Err(TryTakeError::Pending));
+    drop(other);
+    assert_eq!(block_on(fut), Err(Canceled));
+  }
+
+  #[test]
+  fn closed_completes_once_future_is_dropped() {
+    let (fut, ctl) = LocalSignalFuture::<u32>::new();
+    assert!(ctl.closed().now_or_never().is_none());
+    assert!(!ctl.is_closed());
+    drop(fut);
+    assert!(ctl.is_closed());
+    block_on(ctl.closed());
+  }
+}
@@ -0,0 +1,97 @@
+use crate::Canceled;
+use crate::Closed;
+use crate::SignalError;
+use crate::SignalFuture;
+use crate::SignalFutureController;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::Context;
+use core::task::Poll;
+
+/// A controller that converts values before signaling them, returned by `SignalFutureController::map_input`. It behaves like the controller it wraps, including counting as one of its clones until dropped.
+pub struct MapInput<T, F> {
+  ctl: SignalFutureController<T>,
+  f: F,
+}
+
+impl<T, F: Clone> Clone for MapInput<T, F> {
+  fn clone(&self) -> Self {
+    MapInput {
+      ctl: self.ctl.clone(),
+      f: self.f.clone(),
+    }
+  }
+}
+
+impl<T, F> MapInput<T, F> {
+  /// Returns whether the `SignalFuture` has been dropped.
+  pub fn is_closed(&self) -> bool {
+    self.ctl.is_closed()
+  }
+
+  /// Returns a future that completes once the `SignalFuture` has been dropped.
+  pub fn closed(&self) -> Closed<'_, T> {
+    self.ctl.closed()
+  }
+
+  /// Converts `value` and signals the result, as `SignalFutureController::signal` does.
+  pub fn signal<U>(&self, value: U)
+  where
+    F: Fn(U) -> T,
+  {
+    self.ctl.signal((self.f)(value));
+  }
+
+  /// Converts `value` and signals the result, as `SignalFutureController::try_signal` does. The value handed back on failure is the converted one.
+  pub fn try_signal<U>(&self, value: U) -> Result<(), SignalError<T>>
+  where
+    F: Fn(U) -> T,
+  {
+    self.ctl.try_signal((self.f)(value))
+  }
+}
+
+impl<T> SignalFutureController<T> {
+  /// Wraps this controller so it accepts values of another type, converting them with `f` before they are stored. This lets a subsystem that produces some other type resolve the future directly, without a task in between to convert.
+  pub fn map_input<U, F: Fn(U) -> T>(self, f: F) -> MapInput<T, F> {
+    MapInput { ctl: self, f }
+  }
+}
+
+/// Future returned by `SignalFuture::map`. It holds `f` unpinned, so it is only a future if `f` is `Unpin`, as closures that don't capture pinned state are.
+pub struct Map<T, F> {
+  fut: SignalFuture<T>,
+  // Only `None` once the future has resolved.
+  f: Option<F>,
+}
+
+impl<T, R, F: FnOnce(T) -> R + Unpin> Future for Map<T, F> {
+  type Output = Result<R, Canceled>;
+
+  fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+    let this = self.get_mut();
+    let res = match this.fut.poll_with(cx.waker()) {
+      Poll::Ready(res) => res,
+      Poll::Pending => return Poll::Pending,
+    };
+    let f = this.f.take().unwrap();
+    Poll::Ready(res.map(f))
+  }
+}
+
+#[cfg(feature = "futures-core")]
+impl<T, R, F: FnOnce(T) -> R + Unpin> futures_core::FusedFuture for Map<T, F> {
+  fn is_terminated(&self) -> bool {
+    self.fut.terminated
+  }
+}
+
+impl<T> SignalFuture<T> {
+  /// Converts the signaled value with `f` once it is received. Cancellation is passed through unchanged.
+  pub fn map<R, F: FnOnce(T) -> R>(self, f: F) -> Map<T, F> {
+    Map {
+      fut: self,
+      f: Some(f),
+    }
+  }
+}
@@ -0,0 +1,235 @@
+use crate::sync::Mutex;
+use crate::SignalError;
+use crate::SignalFuture;
+use crate::SignalFutureController;
+use alloc::sync::Arc;
+use alloc::sync::Weak;
+use alloc::task::Wake;
+use core::fmt;
+use core::fmt::Debug;
+use core::fmt::Display;
+use core::hash::Hash;
+use core::mem::take;
+use core::task::Waker;
+use std::collections::HashMap;
+
+struct Entry<T> {
+  // Distinguishes this entry from later ones registered under the same key, so a stale removal can't remove the wrong one.
+  id: u64,
+  ctl: SignalFutureController<T>,
+}
+
+struct RegistryState<K, T> {
+  next_id: u64,
+  entries: HashMap<K, Entry<T>>,
+}
+
+type Shared<K, T> = Mutex<RegistryState<K, T>>;
+
+// Registered as the future's closed waker, so dropping the `SignalFuture` removes its entry.
+struct RemoveOnClose<K, T> {
+  registry: Weak<Shared<K, T>>,
+  key: K,
+  id: u64,
+}
+
+impl<K, T> Wake for RemoveOnClose<K, T>
+where
+  K: Hash + Eq + Send + Sync + 'static,
+  T: Send + 'static,
+{
+  fn wake(self: Arc<Self>) {
+    self.wake_by_ref();
+  }
+
+  fn wake_by_ref(self: &Arc<Self>) {
+    let Some(registry) = self.registry.upgrade() else {
+      return;
+    };
+    let removed = {
+      let mut registry = registry.lock();
+      match registry.entries.get(&self.key) {
+        Some(e) if e.id == self.id => registry.entries.remove(&self.key),
+        _ => None,
+      }
+    };
+    // Dropping the controller can wake tasks, so it's done after releasing the lock.
+    drop(removed);
+  }
+}
+
+/// Error returned by `SignalRegistry::register` when a future is already registered under the key. The key is handed back.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DuplicateKey<K>(pub K);
+
+impl<K> Display for DuplicateKey<K> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "key is already registered")
+  }
+}
+
+impl<K: Debug> std::error::Error for DuplicateKey<K> {}
+
+/// Correlates keys with pending `SignalFuture`s, e.g. to match RPC responses with the requests awaiting them by request ID. Each key maps to one future until it is signaled, canceled, or the future is dropped, at which point the key is removed and can be registered again.
+///
+/// Clones share the same registry.
+pub struct SignalRegistry<K, T> {
+  shared_state: Arc<Shared<K, T>>,
+}
+
+impl<K, T> Clone for SignalRegistry<K, T> {
+  fn clone(&self) -> Self {
+    SignalRegistry {
+      shared_state: self.shared_state.clone(),
+    }
+  }
+}
+
+impl<K, T> SignalRegistry<K, T>
+where
+  K: Hash + Eq + Clone + Send + Sync + 'static,
+  T: Send + 'static,
+{
+  pub fn new() -> SignalRegistry<K, T> {
+    SignalRegistry {
+      shared_state: Arc::new(Mutex::new(RegistryState {
+        next_id: 0,
+        entries: HashMap::new(),
+      })),
+    }
+  }
+
+  /// Returns a future that resolves once `key` is signaled. Fails if a future is already pending for `key`.
+  pub fn register(&self, key: K) -> Result<SignalFuture<T>, DuplicateKey<K>> {
+    let mut state = self.shared_state.lock();
+    if state.entries.contains_key(&key) {
+      return Err(DuplicateKey(key));
+    };
+    let id = state.next_id;
+    state.next_id += 1;
+    let (fut, ctl) = SignalFuture::new();
+    let waker = Waker::from(Arc::new(RemoveOnClose {
+      registry: Arc::downgrade(&self.shared_state),
+      key: key.clone(),
+      id,
+    }));
+    // The future was only just created, so it can't be closed yet.
+    let _ = ctl.shared_state.poll_closed(&waker);
+    state.entries.insert(key, Entry { id, ctl });
+    Ok(fut)
+  }
+
+  /// Resolves the future registered under `key` with `value`, and removes the key. If nothing is registered under `key`, or its future has been dropped, the value is handed back as `SignalError::Closed`.
+  pub fn signal(&self, key: &K, value: T) -> Result<(), SignalError<T>> {
+    let Some(entry) = self.remove(key) else {
+      return Err(SignalError::Closed(value));
+    };
+    entry.ctl.try_signal(value)
+  }
+
+  /// Removes `key`, resolving its future with `Err(Canceled)`. Returns whether anything was registered under `key`.
+  pub fn cancel(&self, key: &K) -> bool {
+    self.remove(key).is_some()
+  }
+
+  /// Returns whether a future is pending for `key`.
+  pub fn contains(&self, key: &K) -> bool {
+    self.shared_state.lock().entries.contains_key(key)
+  }
+
+  /// Returns how many futures are pending.
+  pub fn len(&self) -> usize {
+    self.shared_state.lock().entries.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+
+  /// Removes every key, resolving all pending futures with `Err(Canceled)`, e.g. when the connection they were waiting on is lost.
+  pub fn cancel_all(&self) {
+    let entries = take(&mut self.shared_state.lock().entries);
+    drop(entries);
+  }
+
+  // The returned entry should be dropped after the lock is released, which is the case as the guard is a temporary.
+  fn remove(&self, key: &K) -> Option<Entry<T>> {
+    self.shared_state.lock().entries.remove(key)
+  }
+}
+
+impl<K, T> Default for SignalRegistry<K, T>
+where
+  K: Hash + Eq + Clone + Send + Sync + 'static,
+  T: Send + 'static,
+{
+  fn default() -> Self {
+    SignalRegistry::new()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::DuplicateKey;
+  use super::SignalRegistry;
+  use crate::Canceled;
+  use crate::SignalError;
+  use futures::executor::block_on;
+
+  #[test]
+  fn signal_resolves_registered_future() {
+    let registry = SignalRegistry::new();
+    let fut = registry.register(1).unwrap();
+    assert_eq!(registry.signal(&1, "one"), Ok(()));
+    assert!(!registry.contains(&1));
+    assert_eq!(block_on(fut), Ok("one"));
+    assert_eq!(registry.signal(&1, "two"), Err(SignalError::Closed("two")));
+  }
+
+  #[test]
+  fn dropping_future_removes_key() {
+    let registry = SignalRegistry::<u32, ()>::new();
+    let fut = registry.register(1).unwrap();
+    assert!(registry.contains(&1));
+    drop(fut);
+    assert!(!registry.contains(&1));
+    assert!(registry.is_empty());
+  }
+
+  #[test]
+  fn stale_close_keeps_newer_entry() {
+    let registry = SignalRegistry::new();
+    let old = registry.register(1).unwrap();
+    assert_eq!(registry.signal(&1, "old"), Ok(()));
+    let new = registry.register(1).unwrap();
+    // The old future's close removes by key, but must only remove the entry it registered.
+    drop(old);
+    assert!(registry.contains(&1));
+    assert_eq!(registry.signal(&1, "new"), Ok(()));
+    assert_eq!(block_on(new), Ok("new"));
+  }
+
+  #[test]
+  fn rejects_duplicate_key() {
+    let registry = SignalRegistry::<u32, ()>::new();
+    let _fut = registry.register(1).unwrap();
+    assert!(matches!(registry.register(1), Err(DuplicateKey(1))));
+    assert_eq!(registry.len(), 1);
+  }
+
+  #[test]
+  fn cancel_resolves_with_canceled() {
+    let registry = SignalRegistry::<u32, ()>::new();
+    let a = registry.register(1).unwrap();
+    let b = registry.register(2).unwrap();
+    let c = registry.register(3).unwrap();
+    assert!(registry.cancel(&1));
+    assert!(!registry.cancel(&1));
+    assert_eq!(block_on(a), Err(Canceled));
+    assert_eq!(registry.len(), 2);
+    registry.cancel_all();
+    assert!(registry.is_empty());
+    assert_eq!(block_on(b), Err(Canceled));
+    assert_eq!(block_on(c), Err(Canceled));
+  }
+}
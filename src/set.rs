@@ -0,0 +1,270 @@
+use crate::atomic_waker::AtomicWaker;
+use crate::sync::Mutex;
+use crate::Canceled;
+use crate::SignalFuture;
+use alloc::collections::VecDeque;
+use alloc::sync::Arc;
+use alloc::task::Wake;
+use alloc::vec::Vec;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::Context;
+use core::task::Poll;
+use core::task::Waker;
+
+// Slots of entries whose futures have been woken, shared by every entry's waker and the task polling the set.
+struct ReadyQueue {
+  ready: Mutex<VecDeque<usize>>,
+  waker: AtomicWaker,
+}
+
+// The waker given to one entry's future. Waking it queues the entry and wakes the task polling the set, so that task only polls entries that may have completed.
+struct EntryWaker {
+  slot: usize,
+  queue: Arc<ReadyQueue>,
+}
+
+impl Wake for EntryWaker {
+  fn wake(self: Arc<Self>) {
+    self.wake_by_ref();
+  }
+
+  fn wake_by_ref(self: &Arc<Self>) {
+    self.queue.ready.lock().push_back(self.slot);
+    self.queue.waker.wake();
+  }
+}
+
+struct Entry<K, T> {
+  key: K,
+  fut: SignalFuture<T>,
+  waker: Waker,
+}
+
+/// Owns many `SignalFuture`s, each tagged with a key, and yields them as they complete. The task polling the set registers a single waker for all of them, and is only woken to poll the futures that were signaled, so it scales to hundreds of outstanding signals, e.g. the pending requests of one connection.
+///
+/// With the `futures-core` feature, this implements `Stream`, yielding the same items as `next_completed`. Using it as a `Stream` requires the keys to be `Unpin`, which plain keys such as integers and strings are.
+pub struct SignalSet<K, T> {
+  // Slab of entries; freed slots are reused. A slot can still be queued after its entry is removed, in which case the next entry in it is polled once for nothing.
+  entries: Vec<Option<Entry<K, T>>>,
+  free: Vec<usize>,
+  len: usize,
+  queue: Arc<ReadyQueue>,
+}
+
+impl<K, T> SignalSet<K, T> {
+  pub fn new() -> SignalSet<K, T> {
+    SignalSet {
+      entries: Vec::new(),
+      free: Vec::new(),
+      len: 0,
+      queue: Arc::new(ReadyQueue {
+        ready: Mutex::new(VecDeque::new()),
+        waker: AtomicWaker::new(),
+      }),
+    }
+  }
+
+  /// Returns how many futures are still outstanding.
+  pub fn len(&self) -> usize {
+    self.len
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.len == 0
+  }
+
+  /// Adds `fut` to the set, to be yielded with `key` once it completes. Panics if `fut` has already completed.
+  pub fn insert(&mut self, key: K, fut: SignalFuture<T>) {
+    assert!(
+      !fut.terminated,
+      "`SignalSet::insert` given a completed `SignalFuture`"
+    );
+    let slot = self.free.pop().unwrap_or(self.entries.len());
+    let waker = Waker::from(Arc::new(EntryWaker {
+      slot,
+      queue: self.queue.clone(),
+    }));
+    let entry = Entry { key, fut, waker };
+    if slot == self.entries.len() {
+      self.entries.push(Some(entry));
+    } else {
+      self.entries[slot] = Some(entry);
+    };
+    self.len += 1;
+    // The future may already have been signaled, so it needs polling once to register its waker either way.
+    self.queue.ready.lock().push_back(slot);
+    self.queue.waker.wake();
+  }
+
+  /// Polls for the next future to complete, returning its key and outcome, or `None` if the set is empty.
+  pub fn poll_next_completed(
+    &mut self,
+    cx: &mut Context<'_>,
+  ) -> Poll<Option<(K, Result<T, Canceled>)>> {
+    // Register before draining, so a future woken after its slot was checked still wakes this task.
+    self.queue.waker.register(cx.waker());
+    loop {
+      let Some(slot) = self.queue.ready.lock().pop_front() else {
+        break;
+      };
+      let Some(entry) = &mut self.entries[slot] else {
+        continue;
+      };
+      if let Poll::Ready(res) = entry.fut.poll_with(&entry.waker) {
+        let entry = self.entries[slot].take().unwrap();
+        self.free.push(slot);
+        self.len -= 1;
+        return Poll::Ready(Some((entry.key, res)));
+      };
+    }
+    if self.len == 0 {
+      Poll::Ready(None)
+    } else {
+      Poll::Pending
+    }
+  }
+
+  /// Waits for the next future to complete, returning its key and outcome, or `None` if the set is empty.
+  pub fn next_completed(&mut self) -> NextCompleted<'_, K, T> {
+    NextCompleted { set: self }
+  }
+
+  // This and `any` take the set by value, so they aren't shadowed by `StreamExt::all` and `StreamExt::any` when those are in scope.
+  /// Waits for every future in the set to complete, returning their keys and outcomes in the order they completed.
+  pub async fn all(mut self) -> Vec<(K, Result<T, Canceled>)> {
+    let mut results = Vec::with_capacity(self.len);
+    while let Some(res) = self.next_completed().await {
+      results.push(res);
+    }
+    results
+  }
+
+  /// Waits for the first future to be signaled successfully, returning its key and value. Futures that are canceled in the meantime are skipped; if every one of them is, this returns `None`. The remaining futures are dropped along with the set; use `next_completed` to keep waiting on them.
+  pub async fn any(mut self) -> Option<(K, T)> {
+    while let Some((key, res)) = self.next_completed().await {
+      if let Ok(value) = res {
+        return Some((key, value));
+      };
+    }
+    None
+  }
+}
+
+impl<K, T> Default for SignalSet<K, T> {
+  fn default() -> Self {
+    SignalSet::new()
+  }
+}
+
+#[cfg(feature = "futures-core")]
+impl<K: Unpin, T> futures_core::Stream for SignalSet<K, T> {
+  type Item = (K, Result<T, Canceled>);
+
+  fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+    self.get_mut().poll_next_completed(cx)
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    (self.len, Some(self.len))
+  }
+}
+
+/// Future returned by `SignalSet::next_completed`.
+pub struct NextCompleted<'a, K, T> {
+  set: &'a mut SignalSet<K, T>,
+}
+
+impl<K, T> Future for NextCompleted<'_, K, T> {
+  type Output = Option<(K, Result<T, Canceled>)>;
+
+  fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+    self.get_mut().set.poll_next_completed(cx)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::SignalSet;
+  use crate::test_util::poll;
+  use crate::Canceled;
+  use crate::SignalFuture;
+  use crate::SignalFutureController;
+  use alloc::vec;
+  use alloc::vec::Vec;
+  use futures::executor::block_on;
+
+  fn set_of(n: usize) -> (SignalSet<usize, usize>, Vec<SignalFutureController<usize>>) {
+    let mut set = SignalSet::new();
+    let ctls = (0..n)
+      .map(|k| {
+        let (fut, ctl) = SignalFuture::new();
+        set.insert(k, fut);
+        ctl
+      })
+      .collect();
+    (set, ctls)
+  }
+
+  #[test]
+  fn yields_in_completion_order() {
+    let (mut set, ctls) = set_of(4);
+    assert!(poll(&mut set.next_completed()).is_pending());
+    ctls[2].signal(20);
+    ctls[0].signal(0);
+    ctls[3].signal(30);
+    assert_eq!(block_on(set.next_completed()), Some((2, Ok(20))));
+    assert_eq!(block_on(set.next_completed()), Some((0, Ok(0))));
+    assert_eq!(block_on(set.next_completed()), Some((3, Ok(30))));
+    assert!(poll(&mut set.next_completed()).is_pending());
+    assert_eq!(set.len(), 1);
+  }
+
+  #[test]
+  fn reports_canceled_entries() {
+    let (mut set, mut ctls) = set_of(2);
+    drop(ctls.remove(1));
+    assert_eq!(block_on(set.next_completed()), Some((1, Err(Canceled))));
+    ctls[0].signal(5);
+    assert_eq!(block_on(set.all()), vec![(0, Ok(5))]);
+  }
+
+  #[test]
+  fn any_skips_cancellations() {
+    let (set, mut ctls) = set_of(3);
+    let last = ctls.pop().unwrap();
+    ctls.clear();
+    last.signal(7);
+    assert_eq!(block_on(set.any()), Some((2, 7)));
+
+    let (set, ctls) = set_of(2);
+    drop(ctls);
+    assert_eq!(block_on(set.any()), None);
+  }
+
+  #[test]
+  fn stale_wakeups_for_reused_slots_are_ignored() {
+    let mut set = SignalSet::new();
+    let (a, a_ctl) = SignalFuture::new();
+    set.insert("a", a);
+    // Queues slot 0 a second time.
+    a_ctl.signal(1);
+    assert_eq!(block_on(set.next_completed()), Some(("a", Ok(1))));
+    let (b, b_ctl) = SignalFuture::new();
+    set.insert("b", b);
+    // The stale entry for "a" polls "b", which hasn't been signaled.
+    assert!(poll(&mut set.next_completed()).is_pending());
+    b_ctl.signal(2);
+    assert_eq!(block_on(set.next_completed()), Some(("b", Ok(2))));
+    assert_eq!(block_on(set.next_completed()), None);
+  }
+
+  #[test]
+  #[should_panic(expected = "`SignalSet::insert` given a completed `SignalFuture`")]
+  fn insert_rejects_completed_future() {
+    let (mut fut, ctl) = SignalFuture::new();
+    ctl.signal(1);
+    assert_eq!(fut.try_take(), Ok(1));
+    SignalSet::new().insert(0, fut);
+  }
+}
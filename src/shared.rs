@@ -0,0 +1,234 @@
+use crate::sync::Arc;
+use crate::sync::Mutex;
+use crate::waiters::Waiters;
+use crate::Canceled;
+use crate::SignalError;
+use core::future::Future;
+use core::future::IntoFuture;
+use core::pin::Pin;
+use core::task::Context;
+use core::task::Poll;
+use core::task::Waker;
+
+struct SharedState<T> {
+  value: Option<T>,
+  controllers: usize,
+  waiters: Waiters,
+}
+
+fn poll_shared<T: Clone>(
+  shared_state: &Mutex<SharedState<T>>,
+  key: &mut Option<u64>,
+  waker: &Waker,
+) -> Poll<Result<T, Canceled>> {
+  let mut shared_state = shared_state.lock();
+  if let Some(v) = &shared_state.value {
+    let v = v.clone();
+    shared_state.waiters.remove(key);
+    Poll::Ready(Ok(v))
+  } else if shared_state.controllers == 0 {
+    shared_state.waiters.remove(key);
+    Poll::Ready(Err(Canceled))
+  } else {
+    shared_state.waiters.register(key, waker);
+    Poll::Pending
+  }
+}
+
+fn drop_waiter<T>(shared_state: &Mutex<SharedState<T>>, key: &mut Option<u64>) {
+  if key.is_some() {
+    shared_state.lock().waiters.remove(key);
+  };
+}
+
+/// Resolves every handle of the `SharedSignal` it was created with. Like `SignalFutureController`, it can be cloned, and once every clone has been dropped without signaling, all waiters resolve with `Err(Canceled)`.
+pub struct SharedSignalController<T = ()> {
+  shared_state: Arc<Mutex<SharedState<T>>>,
+}
+
+impl<T> Clone for SharedSignalController<T> {
+  fn clone(&self) -> Self {
+    self.shared_state.lock().controllers += 1;
+    SharedSignalController {
+      shared_state: self.shared_state.clone(),
+    }
+  }
+}
+
+impl<T> Drop for SharedSignalController<T> {
+  fn drop(&mut self) {
+    let wakers = {
+      let mut shared_state = self.shared_state.lock();
+      shared_state.controllers -= 1;
+      (shared_state.controllers == 0).then(|| shared_state.waiters.take_all())
+    };
+    for waker in wakers.into_iter().flatten() {
+      waker.wake();
+    }
+  }
+}
+
+impl<T> SharedSignalController<T> {
+  /// Resolves every current and future waiter with a clone of `value`. The signal can only be set once; later values are discarded.
+  pub fn signal(&self, value: T) {
+    let _ = self.try_signal(value);
+  }
+
+  /// Like `signal`, but hands later values back as `SignalError::AlreadySignaled`.
+  pub fn try_signal(&self, value: T) -> Result<(), SignalError<T>> {
+    let wakers = {
+      let mut shared_state = self.shared_state.lock();
+      if shared_state.value.is_some() {
+        return Err(SignalError::AlreadySignaled(value));
+      };
+      shared_state.value = Some(value);
+      shared_state.waiters.take_all()
+    };
+    for waker in wakers {
+      waker.wake();
+    }
+    Ok(())
+  }
+}
+
+/// A broadcast version of `SignalFuture`: any number of tasks can wait on it, and all of them resolve with a clone of the signaled value. This suits one-off notifications that many consumers care about, such as "config loaded" or "leader elected".
+///
+/// Each clone of a `SharedSignal` is a future of its own. Alternatively, a single handle can be awaited by reference (`(&signal).await`) from several places at once, which avoids cloning the handle. As with `SignalFuture`, polling one of these futures again after it has resolved panics, but cloning a handle always gives a fresh future.
+pub struct SharedSignal<T = ()> {
+  shared_state: Arc<Mutex<SharedState<T>>>,
+  key: Option<u64>,
+  terminated: bool,
+}
+
+impl<T> SharedSignal<T> {
+  pub fn new() -> (SharedSignal<T>, SharedSignalController<T>) {
+    let shared_state = Arc::new(Mutex::new(SharedState {
+      value: None,
+      controllers: 1,
+      waiters: Waiters::new(),
+    }));
+
+    (
+      SharedSignal {
+        shared_state: shared_state.clone(),
+        key: None,
+        terminated: false,
+      },
+      SharedSignalController {
+        shared_state: shared_state.clone(),
+      },
+    )
+  }
+}
+
+impl<T> Clone for SharedSignal<T> {
+  fn clone(&self) -> Self {
+    SharedSignal {
+      shared_state: self.shared_state.clone(),
+      key: None,
+      terminated: false,
+    }
+  }
+}
+
+impl<T: Clone> Future for SharedSignal<T> {
+  type Output = Result<T, Canceled>;
+
+  fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+    let this = self.get_mut();
+    assert!(!this.terminated, "`SharedSignal` polled after completion");
+    let res = poll_shared(&this.shared_state, &mut this.key, cx.waker());
+    this.terminated = res.is_ready();
+    res
+  }
+}
+
+#[cfg(feature = "futures-core")]
+impl<T: Clone> futures_core::FusedFuture for SharedSignal<T> {
+  fn is_terminated(&self) -> bool {
+    self.terminated
+  }
+}
+
+impl<T> Drop for SharedSignal<T> {
+  fn drop(&mut self) {
+    drop_waiter(&self.shared_state, &mut self.key);
+  }
+}
+
+impl<'a, T: Clone> IntoFuture for &'a SharedSignal<T> {
+  type IntoFuture = SharedSignalWait<'a, T>;
+  type Output = Result<T, Canceled>;
+
+  fn into_future(self) -> Self::IntoFuture {
+    SharedSignalWait {
+      signal: self,
+      key: None,
+      terminated: false,
+    }
+  }
+}
+
+/// Future returned by awaiting a `&SharedSignal`.
+pub struct SharedSignalWait<'a, T = ()> {
+  signal: &'a SharedSignal<T>,
+  key: Option<u64>,
+  terminated: bool,
+}
+
+impl<T: Clone> Future for SharedSignalWait<'_, T> {
+  type Output = Result<T, Canceled>;
+
+  fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+    let this = self.get_mut();
+    assert!(
+      !this.terminated,
+      "`SharedSignalWait` polled after completion"
+    );
+    let res = poll_shared(&this.signal.shared_state, &mut this.key, cx.waker());
+    this.terminated = res.is_ready();
+    res
+  }
+}
+
+#[cfg(feature = "futures-core")]
+impl<T: Clone> futures_core::FusedFuture for SharedSignalWait<'_, T> {
+  fn is_terminated(&self) -> bool {
+    self.terminated
+  }
+}
+
+impl<T> Drop for SharedSignalWait<'_, T> {
+  fn drop(&mut self) {
+    drop_waiter(&self.signal.shared_state, &mut self.key);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::SharedSignal;
+  use crate::Canceled;
+  use crate::SignalError;
+  use core::future::IntoFuture;
+  use futures::executor::block_on;
+
+  #[test]
+  fn signal_resolves_every_handle_once() {
+    let (signal, ctl) = SharedSignal::new();
+    let other = signal.clone();
+    ctl.signal(1);
+    ctl.signal(2);
+    assert_eq!(ctl.try_signal(3), Err(SignalError::AlreadySignaled(3)));
+    assert_eq!(block_on((&signal).into_future()), Ok(1));
+    assert_eq!(block_on(other), Ok(1));
+    assert_eq!(block_on(signal), Ok(1));
+  }
+
+  #[test]
+  fn dropping_controllers_cancels_every_handle() {
+    let (signal, ctl) = SharedSignal::<u32>::new();
+    drop(ctl);
+    assert_eq!(block_on((&signal).into_future()), Err(Canceled));
+    assert_eq!(block_on(signal.clone()), Err(Canceled));
+  }
+}
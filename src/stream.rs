@@ -0,0 +1,439 @@
+use crate::sync::Arc;
+use crate::sync::Mutex;
+use crate::waiters::Waiters;
+use alloc::collections::VecDeque;
+use core::fmt;
+use core::fmt::Display;
+use core::future::Future;
+use core::mem::take;
+use core::pin::Pin;
+use core::task::Context;
+use core::task::Poll;
+use core::task::Waker;
+
+struct StreamState<T> {
+  buffer: VecDeque<T>,
+  // `None` if the buffer is unbounded.
+  capacity: Option<usize>,
+  receiver_waker: Option<Waker>,
+  // Tasks waiting in `SignalStreamController::send` for room in a bounded buffer. A sender is woken by removing it from this list.
+  senders: Waiters,
+  controllers: usize,
+  // Set by `SignalStreamController::close`. Buffered values are still yielded, but no more can be signaled.
+  closed: bool,
+  receiver_dropped: bool,
+}
+
+impl<T> StreamState<T> {
+  fn is_full(&self) -> bool {
+    self.capacity.is_some_and(|c| self.buffer.len() >= c)
+  }
+
+  // Returns the waker to wake once the lock is released.
+  fn push(&mut self, value: T) -> Result<Option<Waker>, TrySendError<T>> {
+    if self.closed || self.receiver_dropped {
+      return Err(TrySendError::Closed(value));
+    };
+    if self.is_full() {
+      return Err(TrySendError::Full(value));
+    };
+    self.buffer.push_back(value);
+    Ok(self.receiver_waker.take())
+  }
+}
+
+/// Error returned by `SignalStreamController::try_signal` and `SignalStreamController::send` when the value could not be buffered. The value is handed back.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TrySendError<T> {
+  /// The stream was closed or its `SignalStream` dropped, so nothing will receive the value.
+  Closed(T),
+  /// A bounded stream has no room for the value until the receiver catches up. `send` waits for room instead of failing with this.
+  Full(T),
+}
+
+impl<T> TrySendError<T> {
+  /// Returns the value that could not be buffered.
+  pub fn into_inner(self) -> T {
+    match self {
+      TrySendError::Closed(v) => v,
+      TrySendError::Full(v) => v,
+    }
+  }
+}
+
+impl<T> Display for TrySendError<T> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      TrySendError::Closed(_) => write!(f, "signal stream was closed or dropped"),
+      TrySendError::Full(_) => write!(f, "signal stream is full"),
+    }
+  }
+}
+
+#[cfg(feature = "std")]
+impl<T: fmt::Debug> std::error::Error for TrySendError<T> {}
+
+/// Signals values into the `SignalStream` it was created with. It can be cloned to signal from several producers; the stream ends once `close` is called or every clone has been dropped, after yielding any values still buffered.
+pub struct SignalStreamController<T> {
+  shared_state: Arc<Mutex<StreamState<T>>>,
+}
+
+impl<T> Clone for SignalStreamController<T> {
+  fn clone(&self) -> Self {
+    self.shared_state.lock().controllers += 1;
+    SignalStreamController {
+      shared_state: self.shared_state.clone(),
+    }
+  }
+}
+
+impl<T> Drop for SignalStreamController<T> {
+  fn drop(&mut self) {
+    let waker = {
+      let mut shared_state = self.shared_state.lock();
+      shared_state.controllers -= 1;
+      if shared_state.controllers == 0 {
+        shared_state.receiver_waker.take()
+      } else {
+        None
+      }
+    };
+    if let Some(waker) = waker {
+      waker.wake();
+    };
+  }
+}
+
+impl<T> SignalStreamController<T> {
+  /// Returns whether the stream has been closed or its `SignalStream` dropped, so signaled values would be discarded.
+  pub fn is_closed(&self) -> bool {
+    let shared_state = self.shared_state.lock();
+    shared_state.closed || shared_state.receiver_dropped
+  }
+
+  /// Ends the stream once the values already signaled have been yielded. Later signals fail with `TrySendError::Closed`, and so do pending `send`s.
+  pub fn close(&self) {
+    let (waker, senders) = {
+      let mut shared_state = self.shared_state.lock();
+      shared_state.closed = true;
+      (
+        shared_state.receiver_waker.take(),
+        shared_state.senders.take_all(),
+      )
+    };
+    if let Some(waker) = waker {
+      waker.wake();
+    };
+    for waker in senders {
+      waker.wake();
+    }
+  }
+
+  /// Signals `value` without waiting. It is discarded if the stream is closed or a bounded buffer is full; use `try_signal` to get it back, or `send` to wait for room.
+  pub fn signal(&self, value: T) {
+    let _ = self.try_signal(value);
+  }
+
+  /// Like `signal`, but hands the value back if the stream is closed or a bounded buffer is full.
+  pub fn try_signal(&self, value: T) -> Result<(), TrySendError<T>> {
+    let waker = self.shared_state.lock().push(value)?;
+    if let Some(waker) = waker {
+      waker.wake();
+    };
+    Ok(())
+  }
+
+  /// Signals `value`, waiting for room if a bounded buffer is full. This is how producers get backpressure from a slow consumer. The value is handed back as `TrySendError::Closed` if the stream is closed first.
+  pub fn send(&self, value: T) -> SignalStreamSend<'_, T> {
+    SignalStreamSend {
+      shared_state: &self.shared_state,
+      value: Some(value),
+      key: None,
+    }
+  }
+}
+
+/// Future returned by `SignalStreamController::send`. It holds the value unpinned until it is buffered, so it is only a future for `Unpin` values; box any that aren't.
+pub struct SignalStreamSend<'a, T> {
+  shared_state: &'a Mutex<StreamState<T>>,
+  value: Option<T>,
+  key: Option<u64>,
+}
+
+impl<T: Unpin> Future for SignalStreamSend<'_, T> {
+  type Output = Result<(), TrySendError<T>>;
+
+  fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+    let this = self.get_mut();
+    let value = this
+      .value
+      .take()
+      .expect("`SignalStreamSend` polled after completion");
+    let mut shared_state = this.shared_state.lock();
+    match shared_state.push(value) {
+      Ok(waker) => {
+        shared_state.senders.remove(&mut this.key);
+        drop(shared_state);
+        if let Some(waker) = waker {
+          waker.wake();
+        };
+        Poll::Ready(Ok(()))
+      }
+      Err(TrySendError::Full(value)) => {
+        this.value = Some(value);
+        shared_state.senders.register(&mut this.key, cx.waker());
+        Poll::Pending
+      }
+      Err(err) => {
+        shared_state.senders.remove(&mut this.key);
+        Poll::Ready(Err(err))
+      }
+    }
+  }
+}
+
+impl<T> Drop for SignalStreamSend<'_, T> {
+  fn drop(&mut self) {
+    let Some(key) = self.key else {
+      return;
+    };
+    let waker = {
+      let mut shared_state = self.shared_state.lock();
+      if shared_state.senders.contains(key) {
+        shared_state.senders.remove(&mut self.key);
+        None
+      } else {
+        // This sender was woken because room became available, but won't use it, so pass the wakeup on.
+        shared_state.senders.take_first()
+      }
+    };
+    if let Some(waker) = waker {
+      waker.wake();
+    };
+  }
+}
+
+/// A multi-shot version of `SignalFuture`: controllers can signal any number of values, which are yielded in order. With the `futures-core` feature, this implements `Stream`; otherwise, values can be received with `recv`.
+///
+/// The buffer between controllers and the stream is either unbounded, or bounded, in which case `SignalStreamController::send` waits for room so a slow consumer applies backpressure to producers.
+pub struct SignalStream<T> {
+  shared_state: Arc<Mutex<StreamState<T>>>,
+  terminated: bool,
+}
+
+impl<T> SignalStream<T> {
+  fn with_capacity(capacity: Option<usize>) -> (SignalStream<T>, SignalStreamController<T>) {
+    let shared_state = Arc::new(Mutex::new(StreamState {
+      buffer: VecDeque::new(),
+      capacity,
+      receiver_waker: None,
+      senders: Waiters::new(),
+      controllers: 1,
+      closed: false,
+      receiver_dropped: false,
+    }));
+
+    (
+      SignalStream {
+        shared_state: shared_state.clone(),
+        terminated: false,
+      },
+      SignalStreamController {
+        shared_state: shared_state.clone(),
+      },
+    )
+  }
+
+  /// Creates a stream whose buffer grows as needed, so signaling never waits.
+  pub fn unbounded() -> (SignalStream<T>, SignalStreamController<T>) {
+    SignalStream::with_capacity(None)
+  }
+
+  /// Creates a stream that buffers at most `capacity` values. Panics if `capacity` is zero.
+  pub fn bounded(capacity: usize) -> (SignalStream<T>, SignalStreamController<T>) {
+    assert!(capacity > 0, "`SignalStream` capacity must be positive");
+    SignalStream::with_capacity(Some(capacity))
+  }
+
+  /// Polls for the next value, returning `None` once the stream has ended.
+  pub fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Option<T>> {
+    if self.terminated {
+      return Poll::Ready(None);
+    };
+    let mut shared_state = self.shared_state.lock();
+    if let Some(value) = shared_state.buffer.pop_front() {
+      let sender = if shared_state.capacity.is_some() {
+        shared_state.senders.take_first()
+      } else {
+        None
+      };
+      drop(shared_state);
+      if let Some(waker) = sender {
+        waker.wake();
+      };
+      Poll::Ready(Some(value))
+    } else if shared_state.closed || shared_state.controllers == 0 {
+      self.terminated = true;
+      Poll::Ready(None)
+    } else {
+      shared_state.receiver_waker = Some(cx.waker().clone());
+      Poll::Pending
+    }
+  }
+
+  /// Returns a future for the next value, or `None` once the stream has ended.
+  pub fn recv(&mut self) -> SignalStreamRecv<'_, T> {
+    SignalStreamRecv { stream: self }
+  }
+}
+
+#[cfg(feature = "futures-core")]
+impl<T> futures_core::Stream for SignalStream<T> {
+  type Item = T;
+
+  fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+    self.get_mut().poll_recv(cx)
+  }
+}
+
+#[cfg(feature = "futures-core")]
+impl<T> futures_core::FusedStream for SignalStream<T> {
+  fn is_terminated(&self) -> bool {
+    self.terminated
+  }
+}
+
+impl<T> Drop for SignalStream<T> {
+  fn drop(&mut self) {
+    let (buffer, senders) = {
+      let mut shared_state = self.shared_state.lock();
+      shared_state.receiver_dropped = true;
+      (
+        take(&mut shared_state.buffer),
+        shared_state.senders.take_all(),
+      )
+    };
+    // Values nobody will receive are dropped outside the lock, as their destructors could be arbitrarily slow.
+    drop(buffer);
+    for waker in senders {
+      waker.wake();
+    }
+  }
+}
+
+/// Future returned by `SignalStream::recv`.
+pub struct SignalStreamRecv<'a, T> {
+  stream: &'a mut SignalStream<T>,
+}
+
+impl<T> Future for SignalStreamRecv<'_, T> {
+  type Output = Option<T>;
+
+  fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+    self.get_mut().stream.poll_recv(cx)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  extern crate std;
+
+  use super::SignalStream;
+  use super::TrySendError;
+  use crate::test_util::cx;
+  use crate::test_util::poll;
+  use core::future::Future;
+  use core::pin::Pin;
+  use core::task::Context;
+  use core::task::Poll;
+  use futures::task::waker;
+  use futures::task::ArcWake;
+  use std::sync::atomic::AtomicBool;
+  use std::sync::atomic::Ordering::SeqCst;
+  use std::sync::Arc;
+
+  #[derive(Default)]
+  struct Flag(AtomicBool);
+
+  impl ArcWake for Flag {
+    fn wake_by_ref(arc_self: &Arc<Self>) {
+      arc_self.0.store(true, SeqCst);
+    }
+  }
+
+  #[test]
+  fn bounded_send_waits_for_room() {
+    let (mut stream, ctl) = SignalStream::bounded(1);
+    assert_eq!(poll(&mut ctl.send(1)), Poll::Ready(Ok(())));
+    assert_eq!(ctl.try_signal(2), Err(TrySendError::Full(2)));
+    let mut send = ctl.send(2);
+    assert!(poll(&mut send).is_pending());
+    assert!(poll(&mut send).is_pending());
+    assert_eq!(stream.poll_recv(&mut cx()), Poll::Ready(Some(1)));
+    assert_eq!(poll(&mut send), Poll::Ready(Ok(())));
+    assert_eq!(stream.poll_recv(&mut cx()), Poll::Ready(Some(2)));
+    assert!(stream.poll_recv(&mut cx()).is_pending());
+  }
+
+  #[test]
+  fn dropped_sender_passes_room_on() {
+    let (mut stream, ctl) = SignalStream::bounded(1);
+    ctl.signal(1);
+    let mut a = ctl.send(2);
+    let mut b = ctl.send(3);
+    let b_woken = Arc::new(Flag::default());
+    let b_waker = waker(b_woken.clone());
+    assert!(poll(&mut a).is_pending());
+    assert!(Pin::new(&mut b)
+      .poll(&mut Context::from_waker(&b_waker))
+      .is_pending());
+    // Frees room and wakes `a` to use it, but `a` is dropped instead, so `b` must be woken in its place.
+    assert_eq!(stream.poll_recv(&mut cx()), Poll::Ready(Some(1)));
+    assert!(!b_woken.0.load(SeqCst));
+    drop(a);
+    assert!(b_woken.0.load(SeqCst));
+    assert_eq!(poll(&mut b), Poll::Ready(Ok(())));
+    assert_eq!(stream.poll_recv(&mut cx()), Poll::Ready(Some(3)));
+  }
+
+  #[test]
+  fn close_fails_pending_sends_and_ends_after_buffer() {
+    let (mut stream, ctl) = SignalStream::bounded(1);
+    ctl.signal(1);
+    let mut send = ctl.send(2);
+    assert!(poll(&mut send).is_pending());
+    ctl.close();
+    assert_eq!(poll(&mut send), Poll::Ready(Err(TrySendError::Closed(2))));
+    assert_eq!(ctl.try_signal(3), Err(TrySendError::Closed(3)));
+    assert_eq!(stream.poll_recv(&mut cx()), Poll::Ready(Some(1)));
+    assert_eq!(stream.poll_recv(&mut cx()), Poll::Ready(None));
+  }
+
+  #[test]
+  fn dropped_stream_fails_pending_sends() {
+    let (stream, ctl) = SignalStream::bounded(1);
+    ctl.signal(1);
+    let mut send = ctl.send(2);
+    assert!(poll(&mut send).is_pending());
+    drop(stream);
+    assert_eq!(poll(&mut send), Poll::Ready(Err(TrySendError::Closed(2))));
+    assert!(ctl.is_closed());
+  }
+
+  #[test]
+  fn unbounded_yields_in_order_until_controllers_are_dropped() {
+    let (mut stream, ctl) = SignalStream::unbounded();
+    let ctl2 = ctl.clone();
+    for i in 0..100 {
+      ctl.signal(i);
+    }
+    drop(ctl);
+    ctl2.signal(100);
+    drop(ctl2);
+    for i in 0..=100 {
+      assert_eq!(stream.poll_recv(&mut cx()), Poll::Ready(Some(i)));
+    }
+    assert_eq!(stream.poll_recv(&mut cx()), Poll::Ready(None));
+    assert_eq!(stream.poll_recv(&mut cx()), Poll::Ready(None));
+  }
+}
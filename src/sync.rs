@@ -0,0 +1,131 @@
+// Synchronisation primitives used by the crate. Under `--cfg loom`, these are swapped for loom's instrumented versions so the lock-free state machine can be model checked.
+
+// Modules that turn an `Arc` into a `Waker` with `Wake`, or need `Weak`, import `alloc::sync::Arc` directly rather than this one: `Wake` is only implemented for the real `Arc`, and loom's has no `Weak` counterpart.
+#[cfg(not(loom))]
+pub(crate) use alloc::sync::Arc;
+#[cfg(not(loom))]
+pub(crate) use core::hint::spin_loop;
+#[cfg(not(loom))]
+pub(crate) use core::sync::atomic::AtomicUsize;
+#[cfg(loom)]
+pub(crate) use loom::hint::spin_loop;
+#[cfg(loom)]
+pub(crate) use loom::sync::atomic::AtomicUsize;
+#[cfg(loom)]
+pub(crate) use loom::sync::Arc;
+// Without `std` there is no OS to park threads on, so the few locks the crate needs outside its atomic fast paths spin instead.
+#[cfg(all(not(loom), feature = "std"))]
+pub(crate) use parking_lot::Mutex;
+#[cfg(all(not(loom), feature = "std"))]
+pub(crate) use parking_lot::RwLock;
+#[cfg(all(not(loom), feature = "std"))]
+pub(crate) use parking_lot::RwLockReadGuard;
+#[cfg(all(not(loom), not(feature = "std")))]
+pub(crate) use spin::mutex::SpinMutex as Mutex;
+#[cfg(all(not(loom), not(feature = "std")))]
+pub(crate) use spin::rwlock::RwLock;
+#[cfg(all(not(loom), not(feature = "std")))]
+pub(crate) use spin::rwlock::RwLockReadGuard;
+
+#[cfg(loom)]
+pub(crate) struct Mutex<T>(loom::sync::Mutex<T>);
+
+#[cfg(loom)]
+impl<T> Mutex<T> {
+  pub(crate) fn new(value: T) -> Mutex<T> {
+    Mutex(loom::sync::Mutex::new(value))
+  }
+
+  pub(crate) fn lock(&self) -> loom::sync::MutexGuard<'_, T> {
+    self.0.lock().unwrap()
+  }
+}
+
+#[cfg(loom)]
+pub(crate) use loom::sync::RwLockReadGuard;
+
+#[cfg(loom)]
+pub(crate) struct RwLock<T>(loom::sync::RwLock<T>);
+
+#[cfg(loom)]
+impl<T> RwLock<T> {
+  pub(crate) fn new(value: T) -> RwLock<T> {
+    RwLock(loom::sync::RwLock::new(value))
+  }
+
+  pub(crate) fn read(&self) -> RwLockReadGuard<'_, T> {
+    self.0.read().unwrap()
+  }
+
+  pub(crate) fn write(&self) -> loom::sync::RwLockWriteGuard<'_, T> {
+    self.0.write().unwrap()
+  }
+}
+
+#[cfg(loom)]
+pub(crate) use loom::cell::UnsafeCell;
+
+// Mirrors the closure-based API of `loom::cell::UnsafeCell` so the same code compiles in both configurations.
+#[cfg(not(loom))]
+pub(crate) struct UnsafeCell<T>(core::cell::UnsafeCell<T>);
+
+#[cfg(not(loom))]
+impl<T> UnsafeCell<T> {
+  pub(crate) const fn new(value: T) -> UnsafeCell<T> {
+    UnsafeCell(core::cell::UnsafeCell::new(value))
+  }
+
+  pub(crate) fn with_mut<R>(&self, f: impl FnOnce(*mut T) -> R) -> R {
+    f(self.0.get())
+  }
+}
+
+// Without the `std` feature, these run against the `spin` locks.
+#[cfg(all(test, not(loom)))]
+mod tests {
+  extern crate std;
+
+  use super::Arc;
+  use super::Mutex;
+  use super::RwLock;
+  use std::thread;
+  use std::vec::Vec;
+
+  #[test]
+  fn mutex_is_exclusive_across_threads() {
+    let counter: Arc<Mutex<usize>> = Arc::new(Mutex::new(0));
+    let threads: Vec<_> = (0..8)
+      .map(|_| {
+        let counter = counter.clone();
+        thread::spawn(move || {
+          for _ in 0..1000 {
+            *counter.lock() += 1;
+          }
+        })
+      })
+      .collect();
+    for t in threads {
+      t.join().unwrap();
+    }
+    assert_eq!(*counter.lock(), 8000);
+  }
+
+  #[test]
+  fn rwlock_readers_see_whole_writes() {
+    let value: Arc<RwLock<(usize, usize)>> = Arc::new(RwLock::new((0, 0)));
+    let writer = {
+      let value = value.clone();
+      thread::spawn(move || {
+        for i in 1..=1000 {
+          *value.write() = (i, i);
+        }
+      })
+    };
+    for _ in 0..1000 {
+      let (a, b) = *value.read();
+      assert_eq!(a, b);
+    }
+    writer.join().unwrap();
+    assert_eq!(*value.read(), (1000, 1000));
+  }
+}
@@ -0,0 +1,15 @@
+// Helpers shared by the unit tests.
+use core::future::Future;
+use core::pin::Pin;
+use core::task::Context;
+use core::task::Poll;
+use futures::task::noop_waker_ref;
+
+// A context whose waker does nothing, for tests that poll by hand and check readiness themselves.
+pub(crate) fn cx() -> Context<'static> {
+  Context::from_waker(noop_waker_ref())
+}
+
+pub(crate) fn poll<F: Future + Unpin>(fut: &mut F) -> Poll<F::Output> {
+  Pin::new(fut).poll(&mut cx())
+}
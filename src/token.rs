@@ -0,0 +1,163 @@
+use crate::registry::SignalRegistry;
+use crate::SignalError;
+use crate::SignalFuture;
+use core::fmt;
+use core::fmt::Display;
+use core::sync::atomic::AtomicU64;
+use core::sync::atomic::Ordering::Relaxed;
+
+// Shared by every registry, so a token is never valid in a registry other than the one that issued it.
+static NEXT_TOKEN: AtomicU64 = AtomicU64::new(0);
+
+/// An opaque handle to a future issued by a `TokenRegistry`, which can be stored or sent anywhere, such as through a message queue or a job table, and later redeemed to signal that future. With the `serde` feature, it serializes as a plain integer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct SignalToken(u64);
+
+impl SignalToken {
+  /// Returns the raw value, e.g. to store in a database column.
+  pub fn into_raw(self) -> u64 {
+    self.0
+  }
+
+  /// Recreates a token from a value returned by `into_raw`. A value that was never issued is simply rejected when redeemed.
+  pub fn from_raw(raw: u64) -> SignalToken {
+    SignalToken(raw)
+  }
+}
+
+impl Display for SignalToken {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{:016x}", self.0)
+  }
+}
+
+/// Issues `SignalToken`s for new futures and resolves them when tokens are redeemed. Clones share the same registry, so tokens can be redeemed from anywhere in the process.
+///
+/// Stale tokens are rejected without a per-slot generation, as token values are never reused: every token gets a fresh value from a process-wide counter that is never reset. Once a token's future has been signaled, canceled or dropped, redeeming that token again is rejected rather than completing some later future.
+pub struct TokenRegistry<T> {
+  registry: SignalRegistry<u64, T>,
+}
+
+impl<T> Clone for TokenRegistry<T> {
+  fn clone(&self) -> Self {
+    TokenRegistry {
+      registry: self.registry.clone(),
+    }
+  }
+}
+
+impl<T: Send + 'static> TokenRegistry<T> {
+  pub fn new() -> TokenRegistry<T> {
+    TokenRegistry {
+      registry: SignalRegistry::new(),
+    }
+  }
+
+  /// Creates a future along with the token that resolves it.
+  pub fn issue(&self) -> (SignalToken, SignalFuture<T>) {
+    let token = NEXT_TOKEN.fetch_add(1, Relaxed);
+    // Token values are never reused, so the key can't be taken.
+    let fut = self.registry.register(token).unwrap();
+    (SignalToken(token), fut)
+  }
+
+  /// Resolves the future `token` was issued for with `value`. Each token can only be redeemed once; the value is handed back as `SignalError::Closed` if the token is stale, its future was dropped, or it was issued by another registry.
+  pub fn signal(&self, token: SignalToken, value: T) -> Result<(), SignalError<T>> {
+    self.registry.signal(&token.0, value)
+  }
+
+  /// Resolves the future `token` was issued for with `Err(Canceled)`. Returns whether the token was still pending.
+  pub fn cancel(&self, token: SignalToken) -> bool {
+    self.registry.cancel(&token.0)
+  }
+
+  /// Returns whether the future `token` was issued for is still pending.
+  pub fn is_pending(&self, token: SignalToken) -> bool {
+    self.registry.contains(&token.0)
+  }
+
+  /// Returns how many futures are pending.
+  pub fn len(&self) -> usize {
+    self.registry.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.registry.is_empty()
+  }
+}
+
+impl<T: Send + 'static> Default for TokenRegistry<T> {
+  fn default() -> Self {
+    TokenRegistry::new()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::SignalToken;
+  use super::TokenRegistry;
+  use crate::SignalError;
+  use futures::executor::block_on;
+
+  #[test]
+  fn signal_resolves_issued_future() {
+    let registry = TokenRegistry::new();
+    let (token, fut) = registry.issue();
+    assert!(registry.is_pending(token));
+    assert_eq!(registry.signal(token, 1), Ok(()));
+    assert_eq!(block_on(fut), Ok(1));
+    assert!(registry.is_empty());
+  }
+
+  #[test]
+  fn rejects_stale_tokens() {
+    let registry = TokenRegistry::new();
+    let (signaled, _fut) = registry.issue();
+    assert_eq!(registry.signal(signaled, 1), Ok(()));
+    let (canceled, _fut) = registry.issue();
+    assert!(registry.cancel(canceled));
+    let (dropped, fut) = registry.issue();
+    drop(fut);
+    let (later, later_fut) = registry.issue();
+    for stale in [signaled, canceled, dropped] {
+      assert_eq!(registry.signal(stale, 2), Err(SignalError::Closed(2)));
+    }
+    // None of the stale tokens completed the future issued after them.
+    assert!(registry.is_pending(later));
+    assert_eq!(registry.signal(later, 3), Ok(()));
+    assert_eq!(block_on(later_fut), Ok(3));
+  }
+
+  #[test]
+  fn rejects_token_from_another_registry() {
+    let registry = TokenRegistry::<u32>::new();
+    let other = TokenRegistry::new();
+    let (token, _fut) = registry.issue();
+    let (_, _other_fut) = other.issue();
+    assert_eq!(other.signal(token, 1), Err(SignalError::Closed(1)));
+    assert!(registry.is_pending(token));
+  }
+
+  #[test]
+  fn raw_round_trip() {
+    let registry = TokenRegistry::new();
+    let (token, fut) = registry.issue();
+    let token = SignalToken::from_raw(token.into_raw());
+    assert_eq!(registry.signal(token, 1), Ok(()));
+    assert_eq!(block_on(fut), Ok(1));
+  }
+
+  #[cfg(feature = "serde")]
+  #[test]
+  fn serde_round_trip() {
+    let registry = TokenRegistry::new();
+    let (token, fut) = registry.issue();
+    let json = serde_json::to_string(&token).unwrap();
+    assert_eq!(json, token.into_raw().to_string());
+    let token: SignalToken = serde_json::from_str(&json).unwrap();
+    assert_eq!(registry.signal(token, 1), Ok(()));
+    assert_eq!(block_on(fut), Ok(1));
+  }
+}
@@ -0,0 +1,261 @@
+use crate::Canceled;
+use crate::Closed;
+use crate::SignalFuture;
+use crate::SignalFutureController;
+use crate::SignalPolicy;
+use alloc::string::String;
+use core::fmt;
+use core::fmt::Display;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::Context;
+use core::task::Poll;
+#[cfg(feature = "std")]
+use std::panic::catch_unwind;
+#[cfg(feature = "std")]
+use std::panic::UnwindSafe;
+
+/// Error returned by a `TrySignalFuture` that did not succeed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TrySignalError<E> {
+  /// The controller reported a failure with `fail`.
+  Failed(E),
+  /// The controller gave up with `abort`, for reasons that aren't part of the error type `E`.
+  Aborted(String),
+  /// Every controller was dropped without reporting an outcome.
+  Canceled,
+  /// The code that was meant to report an outcome panicked. Holds the panic message if one was available, which is only the case when the panic was caught with `catch_signal`.
+  Panicked(Option<String>),
+}
+
+impl<E> From<Canceled> for TrySignalError<E> {
+  fn from(_: Canceled) -> Self {
+    TrySignalError::Canceled
+  }
+}
+
+impl<E: Display> Display for TrySignalError<E> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      TrySignalError::Failed(e) => write!(f, "signal failed: {e}"),
+      TrySignalError::Aborted(reason) => write!(f, "signal aborted: {reason}"),
+      TrySignalError::Canceled => Display::fmt(&Canceled, f),
+      TrySignalError::Panicked(Some(message)) => write!(f, "signal controller panicked: {message}"),
+      TrySignalError::Panicked(None) => write!(f, "signal controller panicked"),
+    }
+  }
+}
+
+#[cfg(feature = "std")]
+impl<E: fmt::Debug + Display> std::error::Error for TrySignalError<E> {}
+
+/// Resolves the `TrySignalFuture` it was created with, either successfully or with an error. Like `SignalFutureController`, it can be cloned; the first outcome reported by any clone is the one received, and later ones are dropped.
+pub struct TrySignalFutureController<T, E> {
+  inner: SignalFutureController<Result<T, TrySignalError<E>>>,
+  propagate_panics: bool,
+}
+
+impl<T, E> Clone for TrySignalFutureController<T, E> {
+  fn clone(&self) -> Self {
+    TrySignalFutureController {
+      inner: self.inner.clone(),
+      propagate_panics: self.propagate_panics,
+    }
+  }
+}
+
+impl<T, E> Drop for TrySignalFutureController<T, E> {
+  fn drop(&mut self) {
+    #[cfg(feature = "std")]
+    if self.propagate_panics && std::thread::panicking() {
+      self.inner.signal(Err(TrySignalError::Panicked(None)));
+    };
+  }
+}
+
+impl<T, E> TrySignalFutureController<T, E> {
+  /// Returns whether the `TrySignalFuture` has been dropped.
+  pub fn is_closed(&self) -> bool {
+    self.inner.is_closed()
+  }
+
+  /// Returns a future that completes once the `TrySignalFuture` has been dropped.
+  pub fn closed(&self) -> Closed<'_, Result<T, TrySignalError<E>>> {
+    self.inner.closed()
+  }
+
+  /// Makes this controller, and clones made from it afterwards, resolve the future with `Err(TrySignalError::Panicked(None))` if dropped while its thread is panicking, instead of leaving the awaiting task to see a plain `Canceled`. The panic message isn't available at that point; use `catch_signal` to capture it. Without the `std` feature, panics can't be detected and this has no effect.
+  pub fn propagate_panics(mut self) -> Self {
+    self.propagate_panics = true;
+    self
+  }
+
+  /// Resolves the future with `Ok(value)`.
+  pub fn succeed(&self, value: T) {
+    self.inner.signal(Ok(value));
+  }
+
+  /// Resolves the future with `Err(TrySignalError::Failed(error))`.
+  pub fn fail(&self, error: E) {
+    self.inner.signal(Err(TrySignalError::Failed(error)));
+  }
+
+  /// Resolves the future with `Err(TrySignalError::Aborted(reason))`.
+  pub fn abort(&self, reason: impl Into<String>) {
+    self
+      .inner
+      .signal(Err(TrySignalError::Aborted(reason.into())));
+  }
+}
+
+/// A `SignalFuture` for operations that can fail, so the controller reports an outcome with `succeed`, `fail` or `abort` rather than signaling a `Result` by hand. All of the ways it can fail, including every controller being dropped, are folded into one `TrySignalError`, so awaiting it composes with `?` directly.
+pub struct TrySignalFuture<T, E> {
+  inner: SignalFuture<Result<T, TrySignalError<E>>>,
+}
+
+impl<T, E> TrySignalFuture<T, E> {
+  pub fn new() -> (TrySignalFuture<T, E>, TrySignalFutureController<T, E>) {
+    let (inner, ctl) = SignalFuture::with_policy(SignalPolicy::FirstWins);
+    (TrySignalFuture { inner }, TrySignalFutureController {
+      inner: ctl,
+      propagate_panics: false,
+    })
+  }
+
+  /// Returns whether an outcome has been reported and is waiting to be received.
+  pub fn is_signaled(&self) -> bool {
+    self.inner.is_signaled()
+  }
+}
+
+impl<T, E> Future for TrySignalFuture<T, E> {
+  type Output = Result<T, TrySignalError<E>>;
+
+  fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+    self
+      .get_mut()
+      .inner
+      .poll_with(cx.waker())
+      .map(|res| res.unwrap_or_else(|Canceled| Err(TrySignalError::Canceled)))
+  }
+}
+
+#[cfg(feature = "futures-core")]
+impl<T, E> futures_core::FusedFuture for TrySignalFuture<T, E> {
+  fn is_terminated(&self) -> bool {
+    self.inner.terminated
+  }
+}
+
+/// Runs `f` and reports its result through `ctl`: `Ok` succeeds and `Err` fails the future. If `f` panics, the future resolves with `Err(TrySignalError::Panicked)` carrying the panic message, and the panic payload is returned so the caller can still log or rethrow it.
+#[cfg(feature = "std")]
+pub fn catch_signal<T, E>(
+  ctl: TrySignalFutureController<T, E>,
+  f: impl FnOnce() -> Result<T, E> + UnwindSafe,
+) -> std::thread::Result<()> {
+  match catch_unwind(f) {
+    Ok(Ok(value)) => ctl.succeed(value),
+    Ok(Err(error)) => ctl.fail(error),
+    Err(payload) => {
+      let message = payload
+        .downcast_ref::<&str>()
+        .map(|m| String::from(*m))
+        .or_else(|| payload.downcast_ref::<String>().cloned());
+      ctl.inner.signal(Err(TrySignalError::Panicked(message)));
+      return Err(payload);
+    }
+  };
+  Ok(())
+}
+
+// Panic detection needs `std`.
+#[cfg(all(test, feature = "std"))]
+mod tests {
+  use super::catch_signal;
+  use super::TrySignalError;
+  use super::TrySignalFuture;
+  use alloc::string::String;
+  use futures::executor::block_on;
+  use std::panic::panic_any;
+  use std::thread;
+
+  #[test]
+  fn controller_dropped_while_panicking_reports_panic() {
+    let (fut, ctl) = TrySignalFuture::<u32, ()>::new();
+    let ctl = ctl.propagate_panics();
+    let res = thread::spawn(move || {
+      let _ctl = ctl;
+      panic!("worker failed");
+    })
+    .join();
+    assert!(res.is_err());
+    assert_eq!(block_on(fut), Err(TrySignalError::Panicked(None)));
+
+    let (fut, ctl) = TrySignalFuture::<u32, ()>::new();
+    let res = thread::spawn(move || {
+      let _ctl = ctl;
+      panic!("worker failed");
+    })
+    .join();
+    assert!(res.is_err());
+    assert_eq!(block_on(fut), Err(TrySignalError::Canceled));
+  }
+
+  #[test]
+  fn earlier_outcome_wins_over_later_panic() {
+    let (fut, ctl) = TrySignalFuture::<u32, ()>::new();
+    let ctl = ctl.propagate_panics();
+    let other = ctl.clone();
+    ctl.succeed(1);
+    let res = thread::spawn(move || {
+      let _other = other;
+      panic!("worker failed");
+    })
+    .join();
+    assert!(res.is_err());
+    drop(ctl);
+    assert_eq!(block_on(fut), Ok(1));
+  }
+
+  #[test]
+  fn catch_signal_reports_result() {
+    let (fut, ctl) = TrySignalFuture::<u32, String>::new();
+    assert!(catch_signal(ctl, || Ok(1)).is_ok());
+    assert_eq!(block_on(fut), Ok(1));
+
+    let (fut, ctl) = TrySignalFuture::<u32, String>::new();
+    assert!(catch_signal(ctl, || Err(String::from("bad"))).is_ok());
+    assert_eq!(
+      block_on(fut),
+      Err(TrySignalError::Failed(String::from("bad")))
+    );
+  }
+
+  #[test]
+  fn catch_signal_extracts_panic_message() {
+    let (fut, ctl) = TrySignalFuture::<u32, ()>::new();
+    let payload = catch_signal(ctl, || panic!("static message")).unwrap_err();
+    assert_eq!(payload.downcast_ref::<&str>(), Some(&"static message"));
+    assert_eq!(
+      block_on(fut),
+      Err(TrySignalError::Panicked(Some(String::from(
+        "static message"
+      ))))
+    );
+
+    let (fut, ctl) = TrySignalFuture::<u32, ()>::new();
+    let code = 7;
+    let payload = catch_signal(ctl, || panic!("formatted message {code}")).unwrap_err();
+    assert!(payload.downcast_ref::<String>().is_some());
+    assert_eq!(
+      block_on(fut),
+      Err(TrySignalError::Panicked(Some(String::from(
+        "formatted message 7"
+      ))))
+    );
+
+    let (fut, ctl) = TrySignalFuture::<u32, ()>::new();
+    assert!(catch_signal(ctl, || panic_any(7u32)).is_err());
+    assert_eq!(block_on(fut), Err(TrySignalError::Panicked(None)));
+  }
+}
@@ -0,0 +1,60 @@
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::mem::take;
+use core::task::Waker;
+
+// Tasks waiting on some shared condition, for primitives that can have more than one waiter at a time. Each waiting future holds the key it was given on first registration, so it can refresh its waker in place and remove itself when dropped. Keys only increase, so iterating the map visits waiters in the order they started waiting.
+pub(crate) struct Waiters {
+  next_key: u64,
+  wakers: BTreeMap<u64, Waker>,
+}
+
+impl Waiters {
+  pub(crate) const fn new() -> Waiters {
+    Waiters {
+      next_key: 0,
+      wakers: BTreeMap::new(),
+    }
+  }
+
+  // Registers `waker`, or replaces the waker previously registered under `key`. A waiter that has since been woken is added again at the back of the queue.
+  pub(crate) fn register(&mut self, key: &mut Option<u64>, waker: &Waker) {
+    if let Some(existing) = key.and_then(|k| self.wakers.get_mut(&k)) {
+      if !existing.will_wake(waker) {
+        *existing = waker.clone();
+      };
+      return;
+    };
+    let k = self.next_key;
+    self.next_key += 1;
+    self.wakers.insert(k, waker.clone());
+    *key = Some(k);
+  }
+
+  pub(crate) fn contains(&self, key: u64) -> bool {
+    self.wakers.contains_key(&key)
+  }
+
+  // Returns whether the waiter was still registered, i.e. whether it has not been woken.
+  pub(crate) fn remove(&mut self, key: &mut Option<u64>) -> bool {
+    match key.take() {
+      Some(k) => self.wakers.remove(&k).is_some(),
+      None => false,
+    }
+  }
+
+  // Removes the waiter that has been waiting longest, returning its waker to be woken once any lock is released.
+  pub(crate) fn take_first(&mut self) -> Option<Waker> {
+    self.wakers.pop_first().map(|(_, waker)| waker)
+  }
+
+  // Removes every waiter. The returned wakers should be woken after releasing any lock around this list, as waking can run arbitrary code.
+  pub(crate) fn take_all(&mut self) -> impl Iterator<Item = Waker> {
+    take(&mut self.wakers).into_values()
+  }
+
+  // Returns the wakers of every waiter without removing them, for waking them to re-check some other condition.
+  pub(crate) fn wakers(&self) -> Vec<Waker> {
+    self.wakers.values().cloned().collect()
+  }
+}
@@ -0,0 +1,254 @@
+use crate::sync::Arc;
+use crate::sync::Mutex;
+use crate::sync::RwLock;
+use crate::sync::RwLockReadGuard;
+use crate::waiters::Waiters;
+use crate::Canceled;
+use core::future::Future;
+use core::mem::replace;
+use core::ops::Deref;
+use core::pin::Pin;
+use core::task::Context;
+use core::task::Poll;
+
+struct WatchInner {
+  // Incremented on every signal. Each receiver remembers the version it last saw.
+  version: u64,
+  controllers: usize,
+  waiters: Waiters,
+}
+
+struct WatchState<T> {
+  // Kept separate from `inner` so that reading the value never contends with receivers registering for changes.
+  value: RwLock<T>,
+  inner: Mutex<WatchInner>,
+}
+
+/// Updates the value of the `WatchSignal` it was created with. It can be cloned to update from several places; once every clone has been dropped, pending `changed` calls resolve with `Err(Canceled)`.
+pub struct WatchSignalController<T> {
+  shared_state: Arc<WatchState<T>>,
+}
+
+impl<T> Clone for WatchSignalController<T> {
+  fn clone(&self) -> Self {
+    self.shared_state.inner.lock().controllers += 1;
+    WatchSignalController {
+      shared_state: self.shared_state.clone(),
+    }
+  }
+}
+
+impl<T> Drop for WatchSignalController<T> {
+  fn drop(&mut self) {
+    let wakers = {
+      let mut inner = self.shared_state.inner.lock();
+      inner.controllers -= 1;
+      (inner.controllers == 0).then(|| inner.waiters.take_all())
+    };
+    for waker in wakers.into_iter().flatten() {
+      waker.wake();
+    }
+  }
+}
+
+impl<T> WatchSignalController<T> {
+  /// Replaces the current value and notifies every receiver. Receivers that haven't caught up yet only see the latest value, not each intermediate one.
+  pub fn signal(&self, value: T) {
+    let old = replace(&mut *self.shared_state.value.write(), value);
+    let wakers = {
+      let mut inner = self.shared_state.inner.lock();
+      inner.version += 1;
+      inner.waiters.take_all()
+    };
+    for waker in wakers {
+      waker.wake();
+    }
+    drop(old);
+  }
+
+  /// Returns a reference to the current value. The value can't be updated while the reference is held.
+  pub fn borrow(&self) -> WatchRef<'_, T> {
+    WatchRef(self.shared_state.value.read())
+  }
+}
+
+/// A reference to the current value of a `WatchSignal`, returned by `borrow`. It holds a read lock, so it should not be kept across an await or for long.
+pub struct WatchRef<'a, T>(RwLockReadGuard<'a, T>);
+
+impl<T> Deref for WatchRef<'_, T> {
+  type Target = T;
+
+  fn deref(&self) -> &T {
+    &self.0
+  }
+}
+
+/// A signal whose controller can update it any number of times, where receivers only care about the latest value rather than every one, e.g. for config reloads. Each clone of a receiver tracks which version it has seen, so `changed` resolves once per update it hasn't observed yet, and `borrow` always shows the most recent value.
+pub struct WatchSignal<T> {
+  shared_state: Arc<WatchState<T>>,
+  seen: u64,
+}
+
+impl<T> Clone for WatchSignal<T> {
+  fn clone(&self) -> Self {
+    WatchSignal {
+      shared_state: self.shared_state.clone(),
+      seen: self.seen,
+    }
+  }
+}
+
+impl<T> WatchSignal<T> {
+  /// Creates a signal holding `initial`, which receivers consider already seen.
+  pub fn new(initial: T) -> (WatchSignal<T>, WatchSignalController<T>) {
+    let shared_state = Arc::new(WatchState {
+      value: RwLock::new(initial),
+      inner: Mutex::new(WatchInner {
+        version: 0,
+        controllers: 1,
+        waiters: Waiters::new(),
+      }),
+    });
+
+    (
+      WatchSignal {
+        shared_state: shared_state.clone(),
+        seen: 0,
+      },
+      WatchSignalController {
+        shared_state: shared_state.clone(),
+      },
+    )
+  }
+
+  /// Returns a reference to the current value, without marking it as seen.
+  pub fn borrow(&self) -> WatchRef<'_, T> {
+    WatchRef(self.shared_state.value.read())
+  }
+
+  /// Returns a reference to the current value and marks it as seen, so `changed` only resolves for later updates.
+  pub fn borrow_and_update(&mut self) -> WatchRef<'_, T> {
+    // Read the version before the value: if an update lands in between, the value is newer than the version recorded, and `changed` will report that update again rather than miss it.
+    self.seen = self.shared_state.inner.lock().version;
+    WatchRef(self.shared_state.value.read())
+  }
+
+  /// Returns whether the value has been updated since this receiver last saw it.
+  pub fn has_changed(&self) -> bool {
+    self.shared_state.inner.lock().version != self.seen
+  }
+
+  /// Waits until the value is updated after the version this receiver last saw, then marks it as seen. Resolves immediately if an update has already been missed, and with `Err(Canceled)` if every controller is dropped without a further update.
+  pub fn changed(&mut self) -> WatchChanged<'_, T> {
+    WatchChanged {
+      signal: self,
+      key: None,
+    }
+  }
+}
+
+/// Future returned by `WatchSignal::changed`.
+pub struct WatchChanged<'a, T> {
+  signal: &'a mut WatchSignal<T>,
+  key: Option<u64>,
+}
+
+impl<T> Future for WatchChanged<'_, T> {
+  type Output = Result<(), Canceled>;
+
+  fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+    let this = self.get_mut();
+    let mut inner = this.signal.shared_state.inner.lock();
+    if inner.version != this.signal.seen {
+      this.signal.seen = inner.version;
+      inner.waiters.remove(&mut this.key);
+      Poll::Ready(Ok(()))
+    } else if inner.controllers == 0 {
+      inner.waiters.remove(&mut this.key);
+      Poll::Ready(Err(Canceled))
+    } else {
+      inner.waiters.register(&mut this.key, cx.waker());
+      Poll::Pending
+    }
+  }
+}
+
+impl<T> Drop for WatchChanged<'_, T> {
+  fn drop(&mut self) {
+    if self.key.is_some() {
+      self
+        .signal
+        .shared_state
+        .inner
+        .lock()
+        .waiters
+        .remove(&mut self.key);
+    };
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  extern crate std;
+
+  use super::WatchSignal;
+  use crate::Canceled;
+  use futures::executor::block_on;
+  use futures::FutureExt;
+  use std::thread;
+
+  #[test]
+  fn changed_resolves_once_for_missed_updates() {
+    let (mut watch, ctl) = WatchSignal::new(0);
+    assert!(watch.changed().now_or_never().is_none());
+    for i in 1..=3 {
+      ctl.signal(i);
+    }
+    assert_eq!(block_on(watch.changed()), Ok(()));
+    assert_eq!(*watch.borrow(), 3);
+    assert!(watch.changed().now_or_never().is_none());
+  }
+
+  #[test]
+  fn clones_track_their_own_version() {
+    let (mut a, ctl) = WatchSignal::new(0);
+    let mut b = a.clone();
+    ctl.signal(1);
+    assert_eq!(block_on(a.changed()), Ok(()));
+    assert!(!a.has_changed());
+    assert!(b.has_changed());
+    let c = a.clone();
+    assert!(!c.has_changed());
+    assert_eq!(block_on(b.changed()), Ok(()));
+    assert!(!b.has_changed());
+  }
+
+  #[test]
+  fn borrow_and_update_suppresses_next_changed() {
+    let (mut watch, ctl) = WatchSignal::new(0);
+    ctl.signal(1);
+    assert_eq!(*watch.borrow(), 1);
+    assert!(watch.has_changed());
+    assert_eq!(*watch.borrow_and_update(), 1);
+    assert!(!watch.has_changed());
+    assert!(watch.changed().now_or_never().is_none());
+  }
+
+  #[test]
+  fn changed_is_canceled_once_controllers_are_dropped() {
+    let (mut watch, ctl) = WatchSignal::new(0);
+    let other = ctl.clone();
+    let waiter = thread::spawn(move || block_on(watch.changed()));
+    drop(ctl);
+    drop(other);
+    assert_eq!(waiter.join().unwrap(), Err(Canceled));
+
+    let (mut watch, ctl) = WatchSignal::new(0);
+    ctl.signal(1);
+    drop(ctl);
+    // An update made before the last controller was dropped is still reported first.
+    assert_eq!(block_on(watch.changed()), Ok(()));
+    assert_eq!(block_on(watch.changed()), Err(Canceled));
+    assert_eq!(*watch.borrow(), 1);
+  }
+}
@@ -0,0 +1,94 @@
+// Model checks of the lock-free `SignalFuture` state machine. Run with:
+//
+//   RUSTFLAGS="--cfg loom" cargo test --release --test loom
+//
+// `State::lock` spins, and every spin is a point where loom can preempt, so an unbounded search doesn't finish in reasonable time. Unless `LOOM_MAX_PREEMPTIONS` is set, the search is bounded to two preemptions. That skips interleavings needing more, so a pass isn't proof, but most concurrency bugs need only one or two; raise the bound for a more thorough run.
+#![cfg(loom)]
+
+use loom::future::block_on;
+use loom::model::Builder;
+use loom::thread;
+use signal_future::Canceled;
+use signal_future::SignalError;
+use signal_future::SignalFuture;
+
+fn model(f: impl Fn() + Sync + Send + 'static) {
+  let mut builder = Builder::new();
+  if builder.preemption_bound.is_none() {
+    builder.preemption_bound = Some(2);
+  };
+  builder.check(f);
+}
+
+#[test]
+fn signal_vs_poll() {
+  model(|| {
+    let (fut, ctl) = SignalFuture::<usize>::new();
+    let th = thread::spawn(move || ctl.signal(7));
+    assert_eq!(block_on(fut), Ok(7));
+    th.join().unwrap();
+  });
+}
+
+#[test]
+fn last_controller_drop_vs_poll() {
+  model(|| {
+    let (fut, ctl) = SignalFuture::<usize>::new();
+    let ctl2 = ctl.clone();
+    let th = thread::spawn(move || drop(ctl));
+    drop(ctl2);
+    assert_eq!(block_on(fut), Err(Canceled));
+    th.join().unwrap();
+  });
+}
+
+#[test]
+fn try_signal_vs_future_drop() {
+  model(|| {
+    let (fut, ctl) = SignalFuture::<usize>::new();
+    let th = thread::spawn(move || drop(fut));
+    match ctl.try_signal(3) {
+      Ok(()) | Err(SignalError::Closed(3)) => {}
+      Err(e) => panic!("unexpected {e:?}"),
+    };
+    th.join().unwrap();
+    assert!(ctl.is_closed());
+    assert_eq!(ctl.try_signal(4), Err(SignalError::Closed(4)));
+  });
+}
+
+#[test]
+fn closed_vs_future_drop() {
+  model(|| {
+    let (fut, ctl) = SignalFuture::<usize>::new();
+    let th = thread::spawn(move || drop(fut));
+    block_on(ctl.closed());
+    assert!(ctl.is_closed());
+    th.join().unwrap();
+  });
+}
+
+#[test]
+fn peek_vs_store() {
+  model(|| {
+    let (mut fut, ctl) = SignalFuture::<usize>::new();
+    let th = thread::spawn(move || ctl.signal(5));
+    let peeked = fut.peek(|v| *v);
+    assert!(peeked.is_none() || peeked == Some(5));
+    assert_eq!(block_on(fut), Ok(5));
+    th.join().unwrap();
+  });
+}
+
+#[test]
+fn peek_vs_replace() {
+  model(|| {
+    let (mut fut, ctl) = SignalFuture::<usize>::new();
+    ctl.signal(1);
+    let th = thread::spawn(move || ctl.signal(2));
+    let peeked = fut.peek(|v| *v);
+    assert!(peeked == Some(1) || peeked == Some(2));
+    th.join().unwrap();
+    assert_eq!(block_on(fut), Ok(2));
+  });
+}